@@ -0,0 +1,50 @@
+/// Access pattern hints that can be passed to [`libc::madvise`]
+///
+/// See `man 2 madvise` for a detailed description of the individual values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /// Expect page references in sequential order, see [`libc::MADV_SEQUENTIAL`]
+    Sequential,
+    /// Expect page references in random order, see [`libc::MADV_RANDOM`]
+    Random,
+    /// Expect access in the near future, see [`libc::MADV_WILLNEED`]
+    WillNeed,
+    /// Do not expect access in the near future, see [`libc::MADV_DONTNEED`].
+    /// Subsequent accesses of private mappings will see zero-filled pages or the contents of the underlying file.
+    DontNeed,
+    /// Enable transparent huge pages for the range, see [`libc::MADV_HUGEPAGE`]
+    HugePage,
+    /// Disable transparent huge pages for the range, see [`libc::MADV_NOHUGEPAGE`]
+    NoHugePage,
+    /// Exclude the range from core dumps, see [`libc::MADV_DONTDUMP`]
+    DontDump,
+    /// The range is no longer needed and the kernel may free it lazily, see [`libc::MADV_FREE`].
+    /// Only applicable to private anonymous mappings; the contents are undefined afterwards.
+    Free,
+    /// Deactivate the range, making it a more likely reclaim target, see [`libc::MADV_COLD`]
+    Cold,
+    /// Reclaim the range immediately, see [`libc::MADV_PAGEOUT`]
+    PageOut,
+}
+
+impl Advice {
+    pub(crate) fn as_raw(self) -> libc::c_int {
+        match self {
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+            Advice::HugePage => libc::MADV_HUGEPAGE,
+            Advice::NoHugePage => libc::MADV_NOHUGEPAGE,
+            Advice::DontDump => libc::MADV_DONTDUMP,
+            Advice::Free => libc::MADV_FREE,
+            Advice::Cold => libc::MADV_COLD,
+            Advice::PageOut => libc::MADV_PAGEOUT,
+        }
+    }
+
+    /// Returns `true` if applying this advice may change the contents of the mapping
+    pub fn is_destructive(self) -> bool {
+        matches!(self, Advice::DontNeed | Advice::Free)
+    }
+}
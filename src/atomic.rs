@@ -0,0 +1,144 @@
+use crate::{
+    shared_view::{self, AtomicInteger},
+    MapSafe, MemoryMapped, Mode, Writable,
+};
+use std::sync::atomic::{self, Ordering};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Atomic integer types that can be mapped, e.g. to keep counters in a file shared between processes
+///
+/// Since every bit pattern is a valid atomic integer, mappings of them can be initialized safely with
+/// [`MemoryMapped::init`]. The alignment of the atomic type, which may be stricter than that of the
+/// corresponding integer, is verified when the mapping is created.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::{MemoryMapped, OpenOptions};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// let counters: MemoryMapped<[AtomicU64]> =
+///     unsafe { OpenOptions::<[AtomicU64]>::new().read(true).write(true).open_shared_slice("stats.bin").unwrap() }.init();
+///
+/// counters.fetch_add_at(3, 1, Ordering::Relaxed);
+/// println!("{:?}", counters.snapshot());
+/// ```
+pub trait AtomicElement: MapSafe + Sync + sealed::Sealed {
+    /// The corresponding integer type, e.g. `u64` for [`atomic::AtomicU64`]
+    type Value: AtomicInteger<Atomic = Self>;
+
+    #[doc(hidden)]
+    fn load(&self, order: Ordering) -> Self::Value;
+
+    #[doc(hidden)]
+    fn store(&self, value: Self::Value, order: Ordering);
+
+    #[doc(hidden)]
+    fn fetch_add(&self, value: Self::Value, order: Ordering) -> Self::Value;
+
+    #[doc(hidden)]
+    fn fetch_sub(&self, value: Self::Value, order: Ordering) -> Self::Value;
+}
+
+macro_rules! impl_atomic_element {
+    ($($atomic:ty => $t:ty),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $atomic {}
+
+            impl AtomicElement for $atomic {
+                type Value = $t;
+
+                fn load(&self, order: Ordering) -> $t {
+                    <$atomic>::load(self, order)
+                }
+
+                fn store(&self, value: $t, order: Ordering) {
+                    <$atomic>::store(self, value, order)
+                }
+
+                fn fetch_add(&self, value: $t, order: Ordering) -> $t {
+                    <$atomic>::fetch_add(self, value, order)
+                }
+
+                fn fetch_sub(&self, value: $t, order: Ordering) -> $t {
+                    <$atomic>::fetch_sub(self, value, order)
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_element!(
+    atomic::AtomicU8 => u8,
+    atomic::AtomicU16 => u16,
+    atomic::AtomicU32 => u32,
+    atomic::AtomicU64 => u64,
+    atomic::AtomicUsize => usize,
+    atomic::AtomicI8 => i8,
+    atomic::AtomicI16 => i16,
+    atomic::AtomicI32 => i32,
+    atomic::AtomicI64 => i64,
+    atomic::AtomicIsize => isize,
+);
+
+impl<A: AtomicElement, M: Mode> MemoryMapped<[A], M> {
+    /// Atomically loads the element at `ix` with [`Ordering::Relaxed`].
+    /// Unlike other atomic operations this is allowed on read-only mappings, follow it with
+    /// [`atomic::fence`]`(Ordering::Acquire)` to synchronize with the writer.
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn load_relaxed_at(&self, ix: usize) -> A::Value {
+        shared_view::load_read_only(&self[ix])
+    }
+
+    /// Copies out the current values of all elements, each loaded with [`Ordering::Relaxed`],
+    /// see [`MemoryMapped::load_relaxed_at`]
+    pub fn snapshot_relaxed(&self) -> Vec<A::Value> {
+        self.iter().map(shared_view::load_read_only).collect()
+    }
+}
+
+impl<A: AtomicElement, M: Writable> MemoryMapped<[A], M> {
+    /// Atomically loads the element at `ix`.
+    /// Read-only mappings only support [`MemoryMapped::load_relaxed_at`].
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn load_at(&self, ix: usize, order: Ordering) -> A::Value {
+        self[ix].load(order)
+    }
+
+    /// Copies out the current values of all elements, each loaded with [`Ordering::Acquire`].
+    /// The elements are loaded one after the other, so the snapshot as a whole is not atomic.
+    pub fn snapshot(&self) -> Vec<A::Value> {
+        self.iter().map(|elem| elem.load(Ordering::Acquire)).collect()
+    }
+
+    /// Atomically stores `value` to the element at `ix`
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn store_at(&self, ix: usize, value: A::Value, order: Ordering) {
+        self[ix].store(value, order)
+    }
+
+    /// Atomically adds `value` to the element at `ix`, wrapping around on overflow, and returns the previous value
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn fetch_add_at(&self, ix: usize, value: A::Value, order: Ordering) -> A::Value {
+        self[ix].fetch_add(value, order)
+    }
+
+    /// Atomically subtracts `value` from the element at `ix`, wrapping around on overflow,
+    /// and returns the previous value
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn fetch_sub_at(&self, ix: usize, value: A::Value, order: Ordering) -> A::Value {
+        self[ix].fetch_sub(value, order)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    memfd::{duplicate_fd, memfd_create},
+    Error, MemoryMapped, OpenOptions, ReadExec, ReadWrite, Result,
+};
+use std::{
+    ops::{Deref, DerefMut},
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+};
+
+/// A writable buffer for machine code that can be turned into [`ExecutableCode`]
+///
+/// The buffer is never writable and executable at the same time: it starts out as an anonymous
+/// `PROT_READ | PROT_WRITE` mapping and is atomically switched to `PROT_READ | PROT_EXEC`
+/// by [`CodeBuffer::finalize`].
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::CodeBuffer;
+///
+/// let mut buf = CodeBuffer::new(4096).unwrap();
+///
+/// // lea eax, [rdi + 1]; ret
+/// buf[..4].copy_from_slice(&[0x8d, 0x47, 0x01, 0xc3]);
+///
+/// let code = buf.finalize().unwrap();
+/// let inc: extern "C" fn(i32) -> i32 = unsafe { code.function(0) };
+/// assert_eq!(inc(41), 42);
+/// ```
+pub struct CodeBuffer {
+    mapping: MemoryMapped<[u8], ReadWrite>,
+}
+
+/// Machine code that may be executed but not written, created by [`CodeBuffer::finalize`]
+pub struct ExecutableCode {
+    mapping: MemoryMapped<[u8], ReadExec>,
+}
+
+/// A code buffer that is mapped twice from the same memfd, once writable and once executable
+///
+/// This allows patching code while it is executable without either view ever being
+/// writable and executable at the same time.
+pub struct DualMappedCode {
+    fd: OwnedFd,
+    writable: MemoryMapped<[u8], ReadWrite>,
+    executable: MemoryMapped<[u8], ReadExec>,
+}
+
+impl CodeBuffer {
+    /// Creates a zero-initialized, writable code buffer of `len` bytes
+    pub fn new(len: usize) -> Result<Self> {
+        let mapping = MemoryMapped::anonymous_slice(len)?.init();
+        Ok(CodeBuffer { mapping })
+    }
+
+    /// Makes the buffer executable and read-only by calling [`libc::mprotect`]
+    pub fn finalize(self) -> Result<ExecutableCode> {
+        let mapping = self.mapping.make_executable()?;
+        clear_instruction_cache(&mapping);
+
+        Ok(ExecutableCode { mapping })
+    }
+}
+
+impl Deref for CodeBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.mapping
+    }
+}
+
+impl DerefMut for CodeBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.mapping
+    }
+}
+
+impl ExecutableCode {
+    /// Returns a function pointer of type `F` to the code starting at `offset`
+    ///
+    /// # Safety
+    /// - `F` must be a function pointer type, e.g. `extern "C" fn(u32) -> u32`
+    /// - the code at `offset` must be a valid function with the signature and calling convention of `F`
+    /// - the returned pointer must not be called after `self` was dropped
+    ///
+    /// # Panics
+    /// panics if `offset` is out of bounds or `F` is not pointer sized
+    pub unsafe fn function<F: Copy>(&self, offset: usize) -> F {
+        function_at(&self.mapping, offset)
+    }
+
+    /// Makes the code writable again by calling [`libc::mprotect`], e.g. to patch it.
+    /// The code cannot be executed until it is finalized again.
+    pub fn into_code_buffer(self) -> Result<CodeBuffer> {
+        Ok(CodeBuffer { mapping: self.mapping.into_mode()? })
+    }
+}
+
+impl Deref for ExecutableCode {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.mapping
+    }
+}
+
+impl DualMappedCode {
+    /// Creates a zero-initialized memfd of `len` bytes by calling [`libc::memfd_create`]
+    /// and maps it once as `PROT_READ | PROT_WRITE` and once as `PROT_READ | PROT_EXEC`
+    pub fn new(len: usize) -> Result<Self> {
+        let fd = memfd_create(c"memory_mapped_code", libc::MFD_CLOEXEC)?;
+        std::fs::File::from(duplicate_fd(&fd)?)
+            .set_len(len as u64)
+            .map_err(|source| Error::FileResize { path: None, file_len: len, source })?;
+
+        let writable = unsafe {
+            OpenOptions::<[u8]>::new()
+                .byte_len(len)
+                .open_shared_slice_from_fd(&fd)?
+                .init()
+        };
+
+        let executable = unsafe {
+            OpenOptions::<[u8], ReadExec>::new()
+                .byte_len(len)
+                .open_shared_slice_from_fd(&fd)?
+                .init()
+        };
+
+        Ok(DualMappedCode { fd, writable, executable })
+    }
+
+    /// Returns the writable view of the code
+    ///
+    /// Modifications become visible through the executable view immediately, so callers must make sure
+    /// that no other thread is executing the code being patched.
+    pub fn writable(&mut self) -> &mut [u8] {
+        &mut self.writable
+    }
+
+    /// Returns the executable view of the code
+    pub fn executable(&self) -> &[u8] {
+        &self.executable
+    }
+
+    /// Returns a function pointer of type `F` to the code starting at `offset` in the executable view
+    ///
+    /// # Safety
+    /// see [`ExecutableCode::function`]
+    pub unsafe fn function<F: Copy>(&self, offset: usize) -> F {
+        clear_instruction_cache(&self.executable);
+        function_at(&self.executable, offset)
+    }
+}
+
+impl AsFd for DualMappedCode {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+unsafe fn function_at<F: Copy>(code: &[u8], offset: usize) -> F {
+    assert_eq!(
+        std::mem::size_of::<F>(),
+        std::mem::size_of::<*const ()>(),
+        "F must be a function pointer type"
+    );
+    assert!(
+        offset < code.len(),
+        "offset {offset} out of range for code of length {}",
+        code.len()
+    );
+
+    let ptr = code.as_ptr().add(offset);
+    std::mem::transmute_copy(&ptr)
+}
+
+/// Makes sure that the instruction cache does not contain stale code for `code`
+fn clear_instruction_cache(code: &[u8]) {
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        extern "C" {
+            fn __clear_cache(start: *mut libc::c_char, end: *mut libc::c_char);
+        }
+
+        let range = code.as_ptr_range();
+        unsafe { __clear_cache(range.start as *mut _, range.end as *mut _) };
+    }
+
+    // instruction caches are coherent on x86
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let _ = code;
+}
@@ -0,0 +1,368 @@
+use crate::{Advice, LayoutError, MappingLayout, MemoryMapped, Mode, Seals};
+use std::{
+    fmt, io,
+    os::unix::io::RawFd,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error type of all fallible operations of this crate
+///
+/// Every variant carries as much context about the failed operation as is known at the point of failure.
+/// Byte offsets and lengths of operations on an existing mapping are relative to the start of the mapped segment.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Opening or inspecting the file to be mapped failed
+    Open { path: Option<PathBuf>, source: io::Error },
+    /// [`libc::mmap`] failed
+    Map {
+        path: Option<PathBuf>,
+        byte_offset: usize,
+        byte_len: usize,
+        protection: libc::c_int,
+        flags: libc::c_int,
+        source: io::Error,
+    },
+    /// The requested combination of [`libc::mmap`] flags is not supported
+    InvalidFlags { flags: libc::c_int, reason: &'static str },
+    /// [`libc::mremap`] failed, `file_byte_offset` is the offset of the mapped segment in the file
+    Remap {
+        path: Option<PathBuf>,
+        file_byte_offset: usize,
+        byte_len: usize,
+        new_byte_len: usize,
+        flags: libc::c_int,
+        source: io::Error,
+    },
+    /// The mapping cannot be resized without moving it to a different address
+    WouldMove { byte_len: usize, new_byte_len: usize },
+    /// [`libc::munmap`] or [`libc::shmdt`] failed, `file_byte_offset` is the offset of the mapped segment in the file
+    Unmap {
+        path: Option<PathBuf>,
+        file_byte_offset: usize,
+        byte_len: usize,
+        source: io::Error,
+    },
+    /// [`libc::mprotect`] failed
+    Protect {
+        byte_offset: usize,
+        byte_len: usize,
+        protection: libc::c_int,
+        source: io::Error,
+    },
+    /// [`libc::madvise`] failed
+    Advise {
+        byte_offset: usize,
+        byte_len: usize,
+        advice: Advice,
+        source: io::Error,
+    },
+    /// [`libc::mlock2`] failed
+    Lock {
+        byte_offset: usize,
+        byte_len: usize,
+        flags: libc::c_uint,
+        source: io::Error,
+    },
+    /// [`libc::munlock`] failed
+    Unlock {
+        byte_offset: usize,
+        byte_len: usize,
+        source: io::Error,
+    },
+    /// A destructive [`Advice`] was passed to an operation that only accepts non-destructive advice
+    DestructiveAdvice(Advice),
+    /// [`libc::msync`] or [`libc::fdatasync`] failed
+    Sync {
+        byte_offset: usize,
+        byte_len: usize,
+        flags: libc::c_int,
+        source: io::Error,
+    },
+    /// The requested layout is not valid for the mapped type
+    InvalidLayout {
+        path: Option<PathBuf>,
+        byte_offset: usize,
+        byte_len: usize,
+        source: LayoutError,
+    },
+    /// The file is too small to contain the requested segment
+    FileTooSmall {
+        path: Option<PathBuf>,
+        byte_offset: usize,
+        byte_len: usize,
+        file_len: usize,
+    },
+    /// No huge pages of the requested size could be allocated, usually because none are reserved
+    HugePagesUnavailable {
+        page_size: usize,
+        byte_len: usize,
+        source: io::Error,
+    },
+    /// More memory was committed than was reserved by [`crate::Reservation::new`]
+    ReservationExceeded { byte_len: usize, reserved_byte_len: usize },
+    /// Adding or querying the seals of a memfd with [`libc::fcntl`] failed
+    Seal { seals: libc::c_int, source: io::Error },
+    /// The memfd does not carry the seals required for the requested operation
+    MissingSeals { required: Seals, present: Seals },
+    /// A System V shared memory call failed, `id` is the key for [`libc::shmget`] and the segment id otherwise
+    SysV {
+        call: &'static str,
+        id: libc::c_int,
+        source: io::Error,
+    },
+    /// Sending or receiving a file descriptor over a Unix domain socket failed
+    Socket { call: &'static str, source: io::Error },
+    /// A received mapping does not have the layout of the requested type
+    LayoutMismatch {
+        expected: MappingLayout,
+        received: MappingLayout,
+    },
+    /// A futex operation on a mapped word failed
+    Futex {
+        byte_offset: usize,
+        op: libc::c_int,
+        source: io::Error,
+    },
+    /// The address does not lie within the mapped segment
+    AddressOutOfBounds { addr: usize, byte_len: usize },
+    /// Initializing or operating a [`crate::SharedMutex`] failed
+    Mutex { call: &'static str, source: io::Error },
+    /// Growing or shrinking the file backing a mapping to `file_len` bytes failed
+    FileResize {
+        path: Option<PathBuf>,
+        file_len: usize,
+        source: io::Error,
+    },
+    /// Duplicating the file descriptor backing a mapping failed
+    DuplicateFd { fd: RawFd, source: io::Error },
+}
+
+impl Error {
+    /// Attaches `path` to variants that carry a path and do not have one yet
+    pub(crate) fn with_path(mut self, new_path: &Path) -> Self {
+        match &mut self {
+            Error::Open { path, .. }
+            | Error::Map { path, .. }
+            | Error::Remap { path, .. }
+            | Error::Unmap { path, .. }
+            | Error::FileResize { path, .. }
+            | Error::InvalidLayout { path, .. }
+            | Error::FileTooSmall { path, .. } => {
+                path.get_or_insert_with(|| new_path.to_owned());
+            }
+            _ => {}
+        }
+
+        self
+    }
+
+    /// Returns the error reported by the operating system, if any
+    pub fn os_error(&self) -> Option<&io::Error> {
+        match self {
+            Error::Open { source, .. }
+            | Error::Map { source, .. }
+            | Error::Remap { source, .. }
+            | Error::Unmap { source, .. }
+            | Error::Protect { source, .. }
+            | Error::Advise { source, .. }
+            | Error::Lock { source, .. }
+            | Error::Unlock { source, .. }
+            | Error::Sync { source, .. }
+            | Error::HugePagesUnavailable { source, .. }
+            | Error::Seal { source, .. }
+            | Error::SysV { source, .. }
+            | Error::Socket { source, .. }
+            | Error::Futex { source, .. }
+            | Error::Mutex { source, .. }
+            | Error::FileResize { source, .. }
+            | Error::DuplicateFd { source, .. } => Some(source),
+            Error::InvalidFlags { .. }
+            | Error::WouldMove { .. }
+            | Error::ReservationExceeded { .. }
+            | Error::MissingSeals { .. }
+            | Error::LayoutMismatch { .. }
+            | Error::AddressOutOfBounds { .. }
+            | Error::DestructiveAdvice(_)
+            | Error::InvalidLayout { .. }
+            | Error::FileTooSmall { .. } => None,
+        }
+    }
+}
+
+/// Returns the error corresponding to the current value of `errno`
+pub(crate) fn last_os_error() -> io::Error {
+    io::Error::from_raw_os_error(unsafe { *libc::__errno_location() })
+}
+
+struct DisplayPath<'a>(&'a Option<PathBuf>);
+
+impl fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(path) => write!(f, " of {}", path.display()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Open { path, source } => write!(f, "failed to open file{}: {source}", DisplayPath(path)),
+            Error::Map { path, byte_offset, byte_len, protection, flags, source } => write!(
+                f,
+                "failed to map {byte_len} bytes at offset {byte_offset}{} (protection {protection:#x}, flags {flags:#x}): {source}",
+                DisplayPath(path)
+            ),
+            Error::InvalidFlags { flags, reason } => write!(f, "invalid mmap flags {flags:#x}: {reason}"),
+            Error::Remap { path, file_byte_offset, byte_len, new_byte_len, flags, source } => write!(
+                f,
+                "failed to remap {byte_len} bytes at offset {file_byte_offset}{} to {new_byte_len} bytes \
+                 (flags {flags:#x}): {source}",
+                DisplayPath(path)
+            ),
+            Error::WouldMove { byte_len, new_byte_len } => {
+                write!(f, "cannot resize mapping from {byte_len} to {new_byte_len} bytes without moving it")
+            },
+            Error::Unmap { path, file_byte_offset, byte_len, source } => write!(
+                f,
+                "failed to unmap {byte_len} bytes at offset {file_byte_offset}{}: {source}",
+                DisplayPath(path)
+            ),
+            Error::Protect { byte_offset, byte_len, protection, source } => write!(
+                f,
+                "failed to change protection of {byte_len} bytes at offset {byte_offset} to {protection:#x}: {source}"
+            ),
+            Error::Advise { byte_offset, byte_len, advice, source } => {
+                write!(f, "failed to apply {advice:?} to {byte_len} bytes at offset {byte_offset}: {source}")
+            },
+            Error::Lock { byte_offset, byte_len, flags, source } => {
+                write!(f, "failed to lock {byte_len} bytes at offset {byte_offset} (flags {flags:#x}): {source}")
+            },
+            Error::Unlock { byte_offset, byte_len, source } => {
+                write!(f, "failed to unlock {byte_len} bytes at offset {byte_offset}: {source}")
+            },
+            Error::DestructiveAdvice(advice) => write!(f, "{advice:?} may change the contents of the mapping"),
+            Error::Sync { byte_offset, byte_len, flags, source } => {
+                write!(f, "failed to sync {byte_len} bytes at offset {byte_offset} (flags {flags:#x}): {source}")
+            },
+            Error::InvalidLayout { path, byte_offset, byte_len, source } => write!(
+                f,
+                "invalid layout for {byte_len} bytes at offset {byte_offset}{}: {source}",
+                DisplayPath(path)
+            ),
+            Error::FileTooSmall { path, byte_offset, byte_len, file_len } => write!(
+                f,
+                "file{} of {file_len} bytes is too small to map {byte_len} bytes at offset {byte_offset}",
+                DisplayPath(path)
+            ),
+            Error::HugePagesUnavailable { page_size, byte_len, source } => write!(
+                f,
+                "failed to allocate {byte_len} bytes of huge pages of size {page_size}, reserve huge pages in \
+                 /sys/kernel/mm/hugepages/hugepages-{}kB/nr_hugepages: {source}",
+                page_size / 1024
+            ),
+            Error::ReservationExceeded { byte_len, reserved_byte_len } => {
+                write!(f, "cannot commit {byte_len} bytes of a reservation of {reserved_byte_len} bytes")
+            },
+            Error::Seal { seals, source } => write!(f, "failed to seal memfd with {seals:#x}: {source}"),
+            Error::MissingSeals { required, present } => {
+                write!(f, "memfd is sealed with {present:?} but {required:?} is required")
+            },
+            Error::SysV { call, id, source } => write!(f, "{call} failed for {id:#x}: {source}"),
+            Error::Socket { call, source } => write!(f, "{call} failed: {source}"),
+            Error::LayoutMismatch { expected, received } => write!(
+                f,
+                "received a mapping of {} byte elements with type fingerprint {:#x} but expected {} byte elements \
+                 with type fingerprint {:#x}",
+                received.elem_size, received.type_fingerprint, expected.elem_size, expected.type_fingerprint
+            ),
+            Error::Futex { byte_offset, op, source } => {
+                write!(f, "futex operation {op} at byte offset {byte_offset} failed: {source}")
+            },
+            Error::AddressOutOfBounds { addr, byte_len } => {
+                write!(f, "address {addr:#x} does not lie within the mapped segment of {byte_len} bytes")
+            },
+            Error::Mutex { call, source } => write!(f, "{call} failed: {source}"),
+            Error::FileResize { path, file_len, source } => {
+                write!(f, "failed to resize file{} to {file_len} bytes: {source}", DisplayPath(path))
+            },
+            Error::DuplicateFd { fd, source } => write!(f, "failed to duplicate file descriptor {fd}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidLayout { source, .. } => Some(source),
+            _ => self.os_error().map(|e| e as _),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e.os_error() {
+            Some(source) => source.kind(),
+            None => io::ErrorKind::InvalidInput,
+        };
+
+        io::Error::new(kind, e)
+    }
+}
+
+/// The error returned when changing the access mode of a mapping fails, which carries the unchanged mapping
+/// so that it is not lost
+pub struct ModeChangeError<T: ?Sized, M: Mode> {
+    mapped: MemoryMapped<T, M>,
+    error: Box<Error>,
+}
+
+impl<T: ?Sized, M: Mode> ModeChangeError<T, M> {
+    pub(crate) fn new(mapped: MemoryMapped<T, M>, error: Error) -> Self {
+        ModeChangeError { mapped, error: Box::new(error) }
+    }
+
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the mapping, which still has its original mode
+    pub fn into_inner(self) -> MemoryMapped<T, M> {
+        self.mapped
+    }
+
+    pub fn into_parts(self) -> (MemoryMapped<T, M>, Error) {
+        (self.mapped, *self.error)
+    }
+}
+
+impl<T: ?Sized, M: Mode> fmt::Debug for ModeChangeError<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModeChangeError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized, M: Mode> fmt::Display for ModeChangeError<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<T: ?Sized, M: Mode> std::error::Error for ModeChangeError<T, M> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<T: ?Sized, M: Mode> From<ModeChangeError<T, M>> for Error {
+    fn from(e: ModeChangeError<T, M>) -> Self {
+        *e.error
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{error::last_os_error, Error, MemoryMapped, Mode, OpenOptions, Result};
+use std::{
+    io::{Read, Write},
+    mem::MaybeUninit,
+    os::unix::{
+        io::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+        net::UnixStream,
+    },
+};
+
+const LAYOUT_BYTE_LEN: usize = 5 * std::mem::size_of::<u64>();
+
+/// The layout of a shared mapping that is transferred alongside its file descriptor by [`send_fd`]
+///
+/// The receiving side uses it to map the same segment of the file and to verify that it is interpreted
+/// as the same type, see [`MemoryMapped::recv_from`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MappingLayout {
+    /// Offset of the mapped segment in the file in bytes
+    pub byte_offset: u64,
+    /// Length of the mapped segment in bytes
+    pub byte_len: u64,
+    pub elem_size: u64,
+    pub elem_align: u64,
+    /// Identifies the element type, see [`type_fingerprint`]
+    pub type_fingerprint: u64,
+}
+
+impl MappingLayout {
+    /// Describes a segment of `len` elements of type `T` starting at `byte_offset`
+    pub fn of_slice<T>(byte_offset: usize, len: usize) -> Self {
+        MappingLayout {
+            byte_offset: byte_offset as u64,
+            byte_len: (len * std::mem::size_of::<T>()) as u64,
+            elem_size: std::mem::size_of::<T>() as u64,
+            elem_align: std::mem::align_of::<T>() as u64,
+            type_fingerprint: type_fingerprint::<T>(),
+        }
+    }
+
+    /// Returns whether a segment of this layout can be mapped as a slice of `T`
+    pub fn matches<T>(&self) -> bool {
+        self.elem_size == std::mem::size_of::<T>() as u64
+            && self.elem_align == std::mem::align_of::<T>() as u64
+            && self.type_fingerprint == type_fingerprint::<T>()
+            && self.byte_len.is_multiple_of(self.elem_size)
+    }
+
+    fn to_bytes(self) -> [u8; LAYOUT_BYTE_LEN] {
+        let fields = [
+            self.byte_offset,
+            self.byte_len,
+            self.elem_size,
+            self.elem_align,
+            self.type_fingerprint,
+        ];
+        let mut bytes = [0; LAYOUT_BYTE_LEN];
+
+        for (chunk, field) in bytes.chunks_exact_mut(8).zip(fields) {
+            chunk.copy_from_slice(&field.to_ne_bytes());
+        }
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; LAYOUT_BYTE_LEN]) -> Self {
+        let mut fields = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap()));
+
+        let mut next = || fields.next().unwrap();
+
+        MappingLayout {
+            byte_offset: next(),
+            byte_len: next(),
+            elem_size: next(),
+            elem_align: next(),
+            type_fingerprint: next(),
+        }
+    }
+}
+
+/// Returns a fingerprint of the type `T` derived from its name
+///
+/// The name of a type is not guaranteed to be stable across compiler versions, so both processes exchanging
+/// mappings should be built with the same compiler.
+pub fn type_fingerprint<T: ?Sized>() -> u64 {
+    // 64 bit FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in std::any::type_name::<T>().bytes() {
+        hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// Sends `fd` together with `layout` over `stream` by calling [`libc::sendmsg`] with [`libc::SCM_RIGHTS`]
+pub fn send_fd<F: AsFd>(stream: &UnixStream, fd: F, layout: &MappingLayout) -> Result<()> {
+    let bytes = layout.to_bytes();
+    let raw_fd = fd.as_fd().as_raw_fd();
+
+    let mut iov = libc::iovec {
+        iov_base: bytes.as_ptr() as *mut libc::c_void,
+        iov_len: bytes.len(),
+    };
+
+    let mut cmsg_buf = [0u64; 8];
+    let cmsg_space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::c_int>() as u32) } as usize;
+    assert!(cmsg_space <= std::mem::size_of_val(&cmsg_buf));
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_space as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<libc::c_int>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, raw_fd);
+    }
+
+    let sent = unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+
+    if sent < 0 {
+        return Err(Error::Socket { call: "sendmsg", source: last_os_error() });
+    }
+
+    // the descriptor is attached to the first byte, the rest of the layout may follow separately
+    (&*stream)
+        .write_all(&bytes[sent as usize..])
+        .map_err(|source| Error::Socket { call: "write", source })
+}
+
+/// Receives a file descriptor and its layout sent by [`send_fd`] from `stream` by calling [`libc::recvmsg`].
+/// The received descriptor is marked close-on-exec.
+///
+/// The whole message is consumed even if it turns out to be invalid, so the stream can still be used afterwards.
+/// Only if the connection fails while reading the layout, which is reported as an [`Error::Socket`] error for `read`,
+/// the position in the stream is lost and it should be closed.
+pub fn recv_fd(stream: &UnixStream) -> Result<(OwnedFd, MappingLayout)> {
+    let mut bytes = [0u8; LAYOUT_BYTE_LEN];
+
+    let mut iov = libc::iovec {
+        iov_base: bytes.as_mut_ptr() as *mut libc::c_void,
+        iov_len: bytes.len(),
+    };
+
+    let mut cmsg_buf = [0u64; 8];
+
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = std::mem::size_of_val(&cmsg_buf) as _;
+
+    let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+
+    if received < 0 {
+        return Err(Error::Socket { call: "recvmsg", source: last_os_error() });
+    }
+
+    let mut fd = None;
+
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg) as *const libc::c_int;
+                let data_len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+
+                // take ownership of every received descriptor, so that unexpected extra ones are closed
+                for i in 0..data_len / std::mem::size_of::<libc::c_int>() {
+                    let received_fd = OwnedFd::from_raw_fd(std::ptr::read_unaligned(data.add(i)));
+                    fd.get_or_insert(received_fd);
+                }
+            }
+
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    let invalid = |message| Error::Socket {
+        call: "recvmsg",
+        source: std::io::Error::new(std::io::ErrorKind::InvalidData, message),
+    };
+
+    if received == 0 {
+        return Err(invalid("connection closed before a file descriptor was received"));
+    }
+
+    // consume the whole layout before validating the message, so that the next call starts at the next message
+    (&*stream)
+        .read_exact(&mut bytes[received as usize..])
+        .map_err(|source| Error::Socket { call: "read", source })?;
+
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(invalid("control message was truncated"));
+    }
+
+    let fd = fd.ok_or_else(|| invalid("message did not contain a file descriptor"))?;
+
+    Ok((fd, MappingLayout::from_bytes(&bytes)))
+}
+
+impl<T, M: Mode> MemoryMapped<[T], M> {
+    /// Sends the file descriptor backing this mapping together with its layout over `stream`, see [`send_fd`].
+    /// Fails with `EBADF` if the mapping does not keep its backing file descriptor, which is the case for all mappings
+    /// not created by [`MemoryMapped::memfd`], [`crate::SharedMemory::map_slice`] or [`MemoryMapped::recv_from`].
+    pub fn send_to(&self, stream: &UnixStream) -> Result<()> {
+        let layout = MappingLayout::of_slice::<T>(self.mapping.file_byte_offset(), self.len());
+
+        match self.backing_fd() {
+            Some(fd) => send_fd(stream, fd, &layout),
+            None => Err(Error::Socket {
+                call: "sendmsg",
+                source: std::io::Error::from_raw_os_error(libc::EBADF),
+            }),
+        }
+    }
+
+    /// Receives a file descriptor and layout sent by [`MemoryMapped::send_to`] from `stream` and maps the described
+    /// segment as a shared mapping by calling [`OpenOptions::open_shared_slice_from_fd`].
+    /// The received descriptor is kept, so the mapping can be forwarded with [`MemoryMapped::send_to`].
+    ///
+    /// Fails with [`Error::LayoutMismatch`] if the element type of the sent mapping differs from `T`.
+    ///
+    /// # Safety
+    /// see [`OpenOptions::open_shared_slice`]
+    pub unsafe fn recv_from(stream: &UnixStream) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        let (fd, received) = recv_fd(stream)?;
+
+        if !received.matches::<T>() {
+            return Err(Error::LayoutMismatch {
+                expected: MappingLayout::of_slice::<T>(received.byte_offset as usize, 0),
+                received,
+            });
+        }
+
+        let mut mapped = OpenOptions::<[T], M>::new()
+            .byte_offset(received.byte_offset as usize)
+            .byte_len(received.byte_len as usize)
+            .open_shared_slice_from_fd(&fd)?;
+
+        mapped.mapping.set_backing_fd(fd);
+        Ok(mapped)
+    }
+}
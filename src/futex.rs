@@ -0,0 +1,97 @@
+use crate::{error::last_os_error, Error, MemoryMapped, Mode, Result};
+use std::{sync::atomic::AtomicU32, time::Duration};
+
+/// The reason [`MemoryMapped::wait`] returned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WaitOutcome {
+    /// The waiter was woken by [`MemoryMapped::wake`], by a signal or spuriously
+    Woken,
+    /// The word did not contain the expected value, so the waiter did not block
+    ValueMismatch,
+    /// The timeout elapsed before the waiter was woken
+    TimedOut,
+}
+
+impl<T: ?Sized, M: Mode> MemoryMapped<T, M> {
+    /// Blocks until the word `addr`, which has to lie within this mapping, is woken by [`MemoryMapped::wake`]
+    /// or `timeout` elapses by calling [`libc::SYS_futex`] with [`libc::FUTEX_WAIT`].
+    /// Returns immediately if `addr` does not contain `expected`.
+    ///
+    /// The futex is not process private, so waiters and wakers may be different processes that map the same file
+    /// or shared anonymous memory. Spurious wakeups are possible, so the word should be checked again after waking.
+    /// Fails with [`Error::AddressOutOfBounds`] if `addr` does not lie within the mapped segment.
+    pub fn wait(&self, addr: &AtomicU32, expected: u32, timeout: Option<Duration>) -> Result<WaitOutcome> {
+        let byte_offset = self.futex_byte_offset(addr)?;
+
+        let timeout = timeout.map(|timeout| libc::timespec {
+            tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        });
+
+        let timeout_ptr = timeout
+            .as_ref()
+            .map_or(std::ptr::null(), |timeout| timeout as *const libc::timespec);
+
+        let res = unsafe { libc::syscall(libc::SYS_futex, addr.as_ptr(), libc::FUTEX_WAIT, expected, timeout_ptr) };
+
+        if res == 0 {
+            return Ok(WaitOutcome::Woken);
+        }
+
+        let source = last_os_error();
+
+        match source.raw_os_error() {
+            Some(libc::EAGAIN) => Ok(WaitOutcome::ValueMismatch),
+            Some(libc::ETIMEDOUT) => Ok(WaitOutcome::TimedOut),
+            Some(libc::EINTR) => Ok(WaitOutcome::Woken),
+            _ => Err(Error::Futex { byte_offset, op: libc::FUTEX_WAIT, source }),
+        }
+    }
+
+    /// Wakes at most `n` waiters blocked in [`MemoryMapped::wait`] on the word `addr`, which has to lie within
+    /// this mapping, by calling [`libc::SYS_futex`] with [`libc::FUTEX_WAKE`]. Returns the number of woken waiters.
+    ///
+    /// Fails with [`Error::AddressOutOfBounds`] if `addr` does not lie within the mapped segment.
+    pub fn wake(&self, addr: &AtomicU32, n: u32) -> Result<usize> {
+        let byte_offset = self.futex_byte_offset(addr)?;
+        let n = n.min(libc::c_int::MAX as u32);
+
+        let res = unsafe { libc::syscall(libc::SYS_futex, addr.as_ptr(), libc::FUTEX_WAKE, n) };
+
+        if res < 0 {
+            return Err(Error::Futex { byte_offset, op: libc::FUTEX_WAKE, source: last_os_error() });
+        }
+
+        Ok(res as usize)
+    }
+
+    /// Returns the offset of `addr` in the mapped segment if the whole word lies within it
+    fn futex_byte_offset(&self, addr: &AtomicU32) -> Result<usize> {
+        let start = self.mapping.segment_ptr().as_ptr() as usize;
+        let byte_len = self.mapping.segment_byte_len();
+        let addr = addr.as_ptr() as usize;
+
+        match addr.checked_sub(start) {
+            Some(byte_offset) if byte_offset + std::mem::size_of::<AtomicU32>() <= byte_len => Ok(byte_offset),
+            _ => Err(Error::AddressOutOfBounds { addr, byte_len }),
+        }
+    }
+}
+
+impl<M: Mode> MemoryMapped<[AtomicU32], M> {
+    /// Waits on the element at `ix`, see [`MemoryMapped::wait`]
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn wait_at(&self, ix: usize, expected: u32, timeout: Option<Duration>) -> Result<WaitOutcome> {
+        self.wait(&self[ix], expected, timeout)
+    }
+
+    /// Wakes at most `n` waiters of the element at `ix`, see [`MemoryMapped::wake`]
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn wake_at(&self, ix: usize, n: u32) -> Result<usize> {
+        self.wake(&self[ix], n)
+    }
+}
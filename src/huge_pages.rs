@@ -0,0 +1,97 @@
+use crate::{error::last_os_error, page_size, Error, Result};
+use std::{fs, os::unix::io::RawFd, path::Path};
+
+const HUGE_PAGES_DIR: &str = "/sys/kernel/mm/hugepages";
+
+/// The size of the huge pages backing an anonymous mapping, see [`crate::OpenOptions::huge_pages`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HugePageSize {
+    /// The default huge page size of the system, as reported by `Hugepagesize` in `/proc/meminfo`
+    Default,
+    /// 2 MiB huge pages, see [`libc::MAP_HUGE_2MB`]
+    Size2M,
+    /// 1 GiB huge pages, see [`libc::MAP_HUGE_1GB`]
+    Size1G,
+}
+
+impl HugePageSize {
+    /// Returns the size of a single huge page in bytes
+    pub fn byte_size(self) -> Result<usize> {
+        match self {
+            HugePageSize::Default => default_huge_page_size(),
+            HugePageSize::Size2M => Ok(2 << 20),
+            HugePageSize::Size1G => Ok(1 << 30),
+        }
+    }
+
+    /// Returns the flags that have to be passed to [`libc::mmap`] to request huge pages of this size
+    pub(crate) fn mmap_flags(self) -> libc::c_int {
+        match self {
+            HugePageSize::Default => libc::MAP_HUGETLB,
+            HugePageSize::Size2M => libc::MAP_HUGETLB | libc::MAP_HUGE_2MB,
+            HugePageSize::Size1G => libc::MAP_HUGETLB | libc::MAP_HUGE_1GB,
+        }
+    }
+}
+
+/// Returns the huge page sizes supported by the system in bytes, in ascending order,
+/// by reading the entries of `/sys/kernel/mm/hugepages`
+///
+/// A supported size can only be mapped if huge pages of that size are reserved, e.g. by writing to
+/// `/sys/kernel/mm/hugepages/hugepages-<size>kB/nr_hugepages`.
+pub fn huge_page_sizes() -> Result<Vec<usize>> {
+    let open_err = |source| Error::Open { path: Some(HUGE_PAGES_DIR.into()), source };
+
+    let mut sizes = Vec::new();
+
+    for entry in fs::read_dir(HUGE_PAGES_DIR).map_err(open_err)? {
+        let name = entry.map_err(open_err)?.file_name();
+        let kib = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("hugepages-"))
+            .and_then(|name| name.strip_suffix("kB"))
+            .and_then(|kib| kib.parse::<usize>().ok());
+
+        if let Some(kib) = kib {
+            sizes.push(kib * 1024);
+        }
+    }
+
+    sizes.sort_unstable();
+    Ok(sizes)
+}
+
+/// Reads the default huge page size from `/proc/meminfo`
+fn default_huge_page_size() -> Result<usize> {
+    let path = Path::new("/proc/meminfo");
+    let meminfo = fs::read_to_string(path).map_err(|source| Error::Open { path: Some(path.to_owned()), source })?;
+
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("Hugepagesize:"))
+        .and_then(|size| size.trim().strip_suffix("kB"))
+        .and_then(|kib| kib.trim().parse::<usize>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| Error::Open {
+            path: Some(path.to_owned()),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "huge pages are not supported"),
+        })
+}
+
+/// Returns the size of the pages backing mappings of the file referred to by `fd` by calling [`libc::fstatfs`].
+/// This is the huge page size of the mount for files on a hugetlbfs and [`page_size`] for all other files.
+pub(crate) fn fd_page_size(fd: RawFd) -> Result<usize> {
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+
+    if unsafe { libc::fstatfs(fd, stat.as_mut_ptr()) } != 0 {
+        return Err(Error::Open { path: None, source: last_os_error() });
+    }
+
+    let stat = unsafe { stat.assume_init() };
+
+    if stat.f_type == libc::HUGETLBFS_MAGIC {
+        Ok(stat.f_bsize as usize)
+    } else {
+        Ok(page_size())
+    }
+}
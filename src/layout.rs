@@ -0,0 +1,154 @@
+use crate::{error::last_os_error, Error, Result};
+use std::{fmt, os::unix::io::RawFd};
+
+/// Describes why the requested layout of a mapping is invalid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The mapped type is zero sized
+    ZeroSizedType,
+    /// The mapping would be empty
+    ZeroLength,
+    /// The byte offset is not a multiple of the alignment of the mapped type
+    MisalignedOffset { byte_offset: usize, align: usize },
+    /// The byte length of a slice mapping is not a multiple of the size of its elements
+    LengthNotMultipleOfSize { byte_len: usize, elem_size: usize },
+    /// The byte length is smaller than the size of the mapped type
+    LengthTooSmall { byte_len: usize, required: usize },
+    /// The byte length of `len` elements does not fit into a `usize`
+    LengthOverflow { len: usize, elem_size: usize },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::ZeroSizedType => write!(f, "zero sized types cannot be mapped"),
+            LayoutError::ZeroLength => write!(f, "mapping would be empty"),
+            LayoutError::MisalignedOffset { byte_offset, align } => {
+                write!(
+                    f,
+                    "byte offset {byte_offset} is not a multiple of the alignment {align}"
+                )
+            }
+            LayoutError::LengthNotMultipleOfSize { byte_len, elem_size } => {
+                write!(
+                    f,
+                    "byte length {byte_len} is not a multiple of the element size {elem_size}"
+                )
+            }
+            LayoutError::LengthTooSmall { byte_len, required } => {
+                write!(
+                    f,
+                    "byte length {byte_len} is smaller than the size of the mapped type {required}"
+                )
+            }
+            LayoutError::LengthOverflow { len, elem_size } => {
+                write!(f, "byte length of {len} elements of size {elem_size} overflows")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Size and alignment of the mapped type, or of the elements for slice mappings
+pub(crate) struct ElemLayout {
+    size: usize,
+    align: usize,
+    is_slice: bool,
+}
+
+impl ElemLayout {
+    pub(crate) fn sized<T>() -> Self {
+        ElemLayout {
+            size: std::mem::size_of::<T>(),
+            align: std::mem::align_of::<T>(),
+            is_slice: false,
+        }
+    }
+
+    pub(crate) fn slice<T>() -> Self {
+        ElemLayout {
+            size: std::mem::size_of::<T>(),
+            align: std::mem::align_of::<T>(),
+            is_slice: true,
+        }
+    }
+
+    /// Returns the byte length of a slice of `len` elements, failing if it overflows
+    pub(crate) fn slice_byte_len(&self, len: usize) -> Result<usize> {
+        len.checked_mul(self.size).ok_or(Error::InvalidLayout {
+            path: None,
+            byte_offset: 0,
+            byte_len: usize::MAX,
+            source: LayoutError::LengthOverflow { len, elem_size: self.size },
+        })
+    }
+
+    /// Validates the segment `byte_offset..byte_offset + byte_len` of a file of `file_len` bytes, if known,
+    /// and returns the byte length of the mapping. A `byte_len` of zero selects the default length, which is
+    /// the size of the mapped type for sized mappings and the rest of the file for slice mappings.
+    pub(crate) fn resolve_byte_len(&self, byte_offset: usize, byte_len: usize, file_len: Option<usize>) -> Result<usize> {
+        let invalid = |byte_len, source| Error::InvalidLayout { path: None, byte_offset, byte_len, source };
+        let too_small = |byte_len, file_len| Error::FileTooSmall { path: None, byte_offset, byte_len, file_len };
+
+        if self.size == 0 {
+            return Err(invalid(byte_len, LayoutError::ZeroSizedType));
+        }
+
+        if !byte_offset.is_multiple_of(self.align) {
+            return Err(invalid(
+                byte_len,
+                LayoutError::MisalignedOffset { byte_offset, align: self.align },
+            ));
+        }
+
+        let byte_len = match (byte_len, file_len) {
+            (0, _) if !self.is_slice => self.size,
+            (0, Some(file_len)) => file_len.checked_sub(byte_offset).ok_or(too_small(0, file_len))?,
+            (byte_len, _) => byte_len,
+        };
+
+        if byte_len == 0 {
+            return Err(invalid(byte_len, LayoutError::ZeroLength));
+        }
+
+        if !self.is_slice && byte_len < self.size {
+            return Err(invalid(
+                byte_len,
+                LayoutError::LengthTooSmall { byte_len, required: self.size },
+            ));
+        }
+
+        if self.is_slice && !byte_len.is_multiple_of(self.size) {
+            return Err(invalid(
+                byte_len,
+                LayoutError::LengthNotMultipleOfSize { byte_len, elem_size: self.size },
+            ));
+        }
+
+        match file_len {
+            Some(file_len) if byte_offset.checked_add(byte_len).is_none_or(|end| end > file_len) => {
+                Err(too_small(byte_len, file_len))
+            }
+            _ => Ok(byte_len),
+        }
+    }
+}
+
+/// Returns the size of the file referred to by `fd` if it is a regular file by calling [`libc::fstat`].
+/// Other kinds of files, e.g. character devices, do not have a meaningful size.
+pub(crate) fn regular_file_len(fd: RawFd) -> Result<Option<usize>> {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } != 0 {
+        return Err(Error::Open { path: None, source: last_os_error() });
+    }
+
+    let stat = unsafe { stat.assume_init() };
+
+    if stat.st_mode & libc::S_IFMT == libc::S_IFREG {
+        Ok(Some(stat.st_size as usize))
+    } else {
+        Ok(None)
+    }
+}
@@ -1,99 +1,294 @@
-#![feature(pointer_byte_offsets)]
-
+mod advice;
+mod atomic;
+mod code_buffer;
+mod error;
+mod fd_passing;
+mod futex;
+mod huge_pages;
+mod layout;
+mod map_safe;
+mod mapped_vec;
+mod memfd;
+mod mode;
 mod open_options;
 mod raw_memory_mapping;
+mod reservation;
+mod shared_memory;
+mod shared_mutex;
+mod shared_view;
+mod sysv;
+mod thread_shared;
 
+pub use advice::Advice;
+pub use atomic::AtomicElement;
+pub use code_buffer::{CodeBuffer, DualMappedCode, ExecutableCode};
+pub use error::{Error, ModeChangeError, Result};
+pub use fd_passing::{recv_fd, send_fd, type_fingerprint, MappingLayout};
+pub use futex::WaitOutcome;
+pub use huge_pages::{huge_page_sizes, HugePageSize};
+pub use layout::LayoutError;
+pub use map_safe::MapSafe;
+pub use mapped_vec::MappedVec;
+pub use memfd::Seals;
+pub use mode::{Mode, Protection, ReadExec, ReadOnly, ReadWrite, Writable};
 pub use open_options::OpenOptions;
 pub use raw_memory_mapping::page_size;
 use raw_memory_mapping::RawMemoryMapping;
+pub use reservation::Reservation;
+pub use shared_memory::SharedMemory;
+pub use shared_mutex::{LockError, SharedMutex, SharedMutexGuard};
+pub use shared_view::{AtomicInteger, SharedView};
+pub use sysv::{SysVSegment, SysVSegmentStat};
+pub use thread_shared::ThreadShared;
 
 use std::{
     marker::PhantomData,
-    mem::MaybeUninit,
-    ops::{Deref, DerefMut},
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut, RangeBounds},
+    os::unix::io::AsRawFd,
     path::Path,
 };
 
 /// A memory-mapped (sized) object or (unsized) slice
 ///
+/// The access mode `M` determines whether the mapping may be written to, see [`Mode`].
+///
+/// Mappings are created uninitialized, i.e. as `MemoryMapped<MaybeUninit<T>>` or `MemoryMapped<[MaybeUninit<T>]>`.
+/// For types that implement [`MapSafe`] they can be initialized safely with [`MemoryMapped::init`],
+/// otherwise [`MemoryMapped::assume_init`] must be used.
+///
 /// # Example
-/// ```rust
+/// ```no_run
 /// use std::mem::MaybeUninit;
-/// use memory_mapped::MemoryMapped;
+/// use memory_mapped::{MemoryMapped, ReadOnly};
 ///
 /// struct S {
 ///     x: u32,
 ///     y: u32,
 /// }
 ///
-/// let mapped: MemoryMapped<MaybeUninit<S>> = MemoryMapped::open("my_S_object.bin").unwrap();
+/// let mapped: MemoryMapped<MaybeUninit<S>, ReadOnly> = MemoryMapped::open("my_S_object.bin").unwrap();
 /// let mapped = unsafe { mapped.assume_init() };
 ///
 /// println!("{} {}", mapped.x, mapped.y);
 /// ```
-pub struct MemoryMapped<T: ?Sized> {
+pub struct MemoryMapped<T: ?Sized, M: Mode = ReadWrite> {
     mapping: RawMemoryMapping,
-    _marker: PhantomData<T>,
+    _marker: PhantomData<(M, T)>,
 }
 
 /// Iterator over a memory-mapped slice
-pub struct IntoIter<T> {
-    mmap: MemoryMapped<[T]>,
+pub struct IntoIter<T, M: Mode = ReadWrite> {
+    mmap: MemoryMapped<[T], M>,
     cur_ix: usize,
 }
 
-unsafe impl<T: ?Sized> Sync for MemoryMapped<T> {}
-unsafe impl<T: ?Sized> Send for MemoryMapped<T> {}
+// the mapping owns its contents like a `Box`, see `ThreadShared` for deliberately shared memory
+unsafe impl<T: ?Sized + Sync, M: Mode> Sync for MemoryMapped<T, M> {}
+unsafe impl<T: ?Sized + Send, M: Mode> Send for MemoryMapped<T, M> {}
 
-impl<T: ?Sized> Drop for MemoryMapped<T> {
+impl<T: ?Sized, M: Mode> Drop for MemoryMapped<T, M> {
     fn drop(&mut self) {
-        self.mapping.close();
+        // errors cannot be reported from drop, use `MemoryMapped::close` to observe them
+        let _ = self.mapping.close();
     }
 }
 
-impl<T> From<RawMemoryMapping> for MemoryMapped<MaybeUninit<T>> {
+impl<T, M: Mode> From<RawMemoryMapping> for MemoryMapped<MaybeUninit<T>, M> {
     fn from(mapping: RawMemoryMapping) -> Self {
         Self { mapping, _marker: PhantomData }
     }
 }
 
-impl<T> From<RawMemoryMapping> for MemoryMapped<[MaybeUninit<T>]> {
+impl<T, M: Mode> From<RawMemoryMapping> for MemoryMapped<[MaybeUninit<T>], M> {
     fn from(mapping: RawMemoryMapping) -> Self {
         Self { mapping, _marker: PhantomData }
     }
 }
 
-impl<T: ?Sized> MemoryMapped<T> {
+impl<T: ?Sized, M: Mode> MemoryMapped<T, M> {
     /// Returns a new [`OptionOptions`] object to allow more fine grained control over the mapping
-    pub fn options() -> OpenOptions<T> {
+    pub fn options() -> OpenOptions<T, M> {
         OpenOptions::new()
     }
 
     pub fn segment_byte_len(&self) -> usize {
         self.mapping.segment_byte_len()
     }
+
+    /// Unmaps the memory by calling [`libc::munmap`], reporting any error instead of ignoring it like [`Drop`] does
+    pub fn close(self) -> Result<()> {
+        let this = ManuallyDrop::new(self);
+        let mapping = unsafe { std::ptr::read(&this.mapping) };
+
+        mapping.close()
+    }
+
+    /// Synchronously flushes modifications of the mapped segment to the underlying file by calling [`libc::msync`]
+    /// with [`libc::MS_SYNC`]. This only has an effect on shared mappings.
+    pub fn flush(&self) -> Result<()> {
+        self.mapping.sync(0..self.segment_byte_len(), libc::MS_SYNC)
+    }
+
+    /// Schedules modifications of the mapped segment to be written to the underlying file by calling [`libc::msync`]
+    /// with [`libc::MS_ASYNC`], returning without waiting for the writes to complete
+    pub fn flush_async(&self) -> Result<()> {
+        self.mapping.sync(0..self.segment_byte_len(), libc::MS_ASYNC)
+    }
+
+    /// Like [`MemoryMapped::flush`] but additionally calls [`libc::fdatasync`] on `file`, which must be the file
+    /// backing this mapping, to make sure that metadata required to read the data back (e.g. the file size) is durable
+    pub fn flush_and_sync<F: AsRawFd>(&self, file: &F) -> Result<()> {
+        self.flush()?;
+        raw_memory_mapping::sync_data(file.as_raw_fd())
+    }
+
+    /// Applies `advice` to the whole mapped segment by calling [`libc::madvise`]
+    ///
+    /// Destructive advice (see [`Advice::is_destructive`]) is rejected with [`Error::DestructiveAdvice`],
+    /// use [`MemoryMapped::advise_unchecked`] instead.
+    pub fn advise(&self, advice: Advice) -> Result<()> {
+        reject_destructive(advice)?;
+        unsafe { self.advise_unchecked(advice) }
+    }
+
+    /// Applies `advice` to the whole mapped segment by calling [`libc::madvise`]
+    ///
+    /// # Safety
+    /// if `advice` is destructive the caller must ensure that the contents of the mapping are still valid
+    /// values of `T` after the kernel discarded the affected pages
+    pub unsafe fn advise_unchecked(&self, advice: Advice) -> Result<()> {
+        self.mapping.advise(0..self.segment_byte_len(), advice)
+    }
+
+    /// Faults in every page of the mapped segment by advising [`Advice::WillNeed`] and touching each page,
+    /// so that later accesses do not stall on page faults. See also [`OpenOptions::populate`].
+    pub fn prefault(&self) -> Result<()> {
+        self.mapping.prefault(1)
+    }
+
+    /// Like [`MemoryMapped::prefault`] but distributes the pages over up to `threads` threads,
+    /// which speeds up faulting in large file backed mappings
+    pub fn prefault_parallel(&self, threads: usize) -> Result<()> {
+        self.mapping.prefault(threads)
+    }
+
+    /// Locks the mapped segment into memory by calling [`libc::mlock2`], faulting in all pages immediately.
+    /// The amount of locked memory is limited by `RLIMIT_MEMLOCK`.
+    pub fn mlock(&self) -> Result<()> {
+        self.mapping.lock(0..self.segment_byte_len(), 0)
+    }
+
+    /// Like [`MemoryMapped::mlock`] but passes [`libc::MLOCK_ONFAULT`], so pages are only locked once they are accessed
+    pub fn mlock_on_fault(&self) -> Result<()> {
+        self.mapping.lock(0..self.segment_byte_len(), libc::MLOCK_ONFAULT)
+    }
+
+    /// Unlocks the mapped segment by calling [`libc::munlock`], allowing its pages to be swapped out again
+    pub fn munlock(&self) -> Result<()> {
+        self.mapping.unlock(0..self.segment_byte_len())
+    }
+
+    /// Changes the protection of the whole mapping by calling [`libc::mprotect`]
+    ///
+    /// # Safety
+    /// the caller must ensure that the mapping is not accessed in a way `protection` does not permit,
+    /// e.g. through [`Deref`] after removing [`Protection::READ`]
+    pub unsafe fn protect(&self, protection: Protection) -> Result<()> {
+        self.mapping.protect(protection.as_raw())
+    }
+
+    /// Makes the mapping read-only and executable by calling [`libc::mprotect`].
+    /// On failure the unchanged mapping is returned as part of the error.
+    pub fn make_executable(self) -> std::result::Result<MemoryMapped<T, ReadExec>, ModeChangeError<T, M>> {
+        self.into_mode()
+    }
+
+    /// Changes the protection of the whole mapping to the one of mode `N` by calling [`libc::mprotect`]
+    fn into_mode<N: Mode>(self) -> std::result::Result<MemoryMapped<T, N>, ModeChangeError<T, M>> {
+        if let Err(e) = self.mapping.protect(N::PROTECTION) {
+            return Err(ModeChangeError::new(self, e));
+        }
+
+        let this = ManuallyDrop::new(self);
+        Ok(MemoryMapped {
+            mapping: unsafe { std::ptr::read(&this.mapping) },
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: ?Sized> MemoryMapped<T, ReadWrite> {
+    /// Makes the mapping read-only by calling [`libc::mprotect`].
+    /// On failure the unchanged mapping is returned as part of the error.
+    pub fn into_read_only(self) -> std::result::Result<MemoryMapped<T, ReadOnly>, ModeChangeError<T, ReadWrite>> {
+        self.into_mode()
+    }
+
+    /// Finishes initialization of the mapping by making it read-only, see [`MemoryMapped::into_read_only`].
+    /// Any write through a pointer that still refers to the mapping will fault immediately afterwards.
+    pub fn freeze(self) -> std::result::Result<MemoryMapped<T, ReadOnly>, ModeChangeError<T, ReadWrite>> {
+        self.into_read_only()
+    }
+}
+
+impl<T: ?Sized> MemoryMapped<T, ReadOnly> {
+    /// Makes the mapping writable by calling [`libc::mprotect`].
+    /// This fails for shared mappings of files that were not opened for writing,
+    /// in which case the unchanged mapping is returned as part of the error.
+    pub fn into_read_write(self) -> std::result::Result<MemoryMapped<T, ReadWrite>, ModeChangeError<T, ReadOnly>> {
+        self.into_mode()
+    }
 }
 
-impl<T> MemoryMapped<MaybeUninit<T>> {
-    pub unsafe fn assume_init(self) -> MemoryMapped<T> {
+fn reject_destructive(advice: Advice) -> Result<()> {
+    if advice.is_destructive() {
+        return Err(Error::DestructiveAdvice(advice));
+    }
+
+    Ok(())
+}
+
+impl<T, M: Mode> MemoryMapped<MaybeUninit<T>, M> {
+    /// # Safety
+    /// the caller must ensure that the mapped memory contains a properly initialized `T`
+    pub unsafe fn assume_init(self) -> MemoryMapped<T, M> {
         std::mem::transmute(self)
     }
 }
 
-impl<T> MemoryMapped<[MaybeUninit<T>]> {
-    pub unsafe fn assume_init(self) -> MemoryMapped<[T]> {
+impl<T: MapSafe, M: Mode> MemoryMapped<MaybeUninit<T>, M> {
+    /// Safely initializes the mapping, since every bit pattern is a valid `T`
+    pub fn init(self) -> MemoryMapped<T, M> {
+        // SAFETY: `T: MapSafe`, so the mapped memory always contains a valid `T`
+        unsafe { self.assume_init() }
+    }
+}
+
+impl<T, M: Mode> MemoryMapped<[MaybeUninit<T>], M> {
+    /// # Safety
+    /// the caller must ensure that every element of the mapped slice is properly initialized
+    pub unsafe fn assume_init(self) -> MemoryMapped<[T], M> {
         std::mem::transmute(self)
     }
 }
 
+impl<T: MapSafe, M: Mode> MemoryMapped<[MaybeUninit<T>], M> {
+    /// Safely initializes the mapping, since every bit pattern is a valid `T`
+    pub fn init(self) -> MemoryMapped<[T], M> {
+        // SAFETY: `T: MapSafe`, so every element of the mapped memory is a valid `T`
+        unsafe { self.assume_init() }
+    }
+}
+
 impl<T> MemoryMapped<T> {
     /// Attempts to memory map a file with [`libc::mmap`] as a read-only, private mapping
     ///
     /// # Safety
     /// begins the object lifetime of a `T`, the caller must ensure that
     /// the created value is properly initialized
-    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
-        OpenOptions::<T>::new().read(true).open(path)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MemoryMapped<MaybeUninit<T>, ReadOnly>> {
+        OpenOptions::<T, ReadOnly>::new().read(true).open(path)
     }
 
     /// Attempts to memory map a file with [`libc::mmap`] as a read-write, private mapping
@@ -102,13 +297,24 @@ impl<T> MemoryMapped<T> {
     /// # Safety
     /// begins the object lifetime of a `T`, the caller must ensure that
     /// the created value is properly initialized
-    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<MemoryMapped<MaybeUninit<T>>> {
         OpenOptions::<T>::new()
             .read(true)
             .write(true)
             .create_new(true)
             .open(path)
     }
+
+    /// Creates a zero-initialized, read-write, private mapping that is not backed by a file
+    pub fn anonymous() -> Result<MemoryMapped<MaybeUninit<T>>> {
+        OpenOptions::<T>::new().read(true).write(true).open_anonymous()
+    }
+
+    /// Creates a zero-initialized, read-write, shared mapping that is not backed by a file.
+    /// The mapping will be shared with child processes created by `fork`.
+    pub fn anonymous_shared() -> Result<MemoryMapped<MaybeUninit<T>>> {
+        OpenOptions::<T>::new().read(true).write(true).open_shared_anonymous()
+    }
 }
 
 impl<T> MemoryMapped<[T]> {
@@ -117,8 +323,8 @@ impl<T> MemoryMapped<[T]> {
     /// # Safety
     /// begins the object lifetime of a `T`, the caller must ensure that
     /// the created value is properly initialized
-    pub fn open_slice<P: AsRef<Path>>(path: P) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
-        OpenOptions::<[T]>::new().read(true).open_slice(path)
+    pub fn open_slice<P: AsRef<Path>>(path: P) -> Result<MemoryMapped<[MaybeUninit<T>], ReadOnly>> {
+        OpenOptions::<[T], ReadOnly>::new().read(true).open_slice(path)
     }
 
     /// Attempts to memory map a file with [`libc::mmap`] as a read-write, private mapping
@@ -127,16 +333,35 @@ impl<T> MemoryMapped<[T]> {
     /// # Safety
     /// begins the object lifetime of a `T`, the caller must ensure that
     /// the created value is properly initialized
-    pub fn create_slice<P: AsRef<Path>>(path: P) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
+    pub fn create_slice<P: AsRef<Path>>(path: P) -> Result<MemoryMapped<[MaybeUninit<T>]>> {
         OpenOptions::<[T]>::new()
             .read(true)
             .write(true)
             .create_new(true)
             .open_slice(path)
     }
+
+    /// Creates a zero-initialized, read-write, private mapping of `len` elements that is not backed by a file
+    pub fn anonymous_slice(len: usize) -> Result<MemoryMapped<[MaybeUninit<T>]>> {
+        OpenOptions::<[T]>::new()
+            .read(true)
+            .write(true)
+            .len(len)
+            .open_anonymous_slice()
+    }
+
+    /// Creates a zero-initialized, read-write, shared mapping of `len` elements that is not backed by a file.
+    /// The mapping will be shared with child processes created by `fork`.
+    pub fn anonymous_shared_slice(len: usize) -> Result<MemoryMapped<[MaybeUninit<T>]>> {
+        OpenOptions::<[T]>::new()
+            .read(true)
+            .write(true)
+            .len(len)
+            .open_shared_anonymous_slice()
+    }
 }
 
-impl<T> MemoryMapped<[T]> {
+impl<T, M: Mode> MemoryMapped<[T], M> {
     pub fn as_slice(&self) -> &[T] {
         unsafe {
             &*std::ptr::slice_from_raw_parts(
@@ -145,7 +370,9 @@ impl<T> MemoryMapped<[T]> {
             )
         }
     }
+}
 
+impl<T, M: Writable> MemoryMapped<[T], M> {
     pub fn as_slice_mut(&mut self) -> &mut [T] {
         unsafe {
             &mut *std::ptr::slice_from_raw_parts_mut(
@@ -156,42 +383,157 @@ impl<T> MemoryMapped<[T]> {
     }
 }
 
-impl<T> MemoryMapped<[MaybeUninit<T>]> {
+impl<T, M: Mode> MemoryMapped<[T], M> {
+    /// Synchronously flushes modifications of the elements in `range` to the underlying file by calling [`libc::msync`].
+    /// The range is extended to cover whole pages.
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub fn flush_range<R: RangeBounds<usize>>(&self, range: R) -> Result<()> {
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, self.len(), std::mem::size_of::<T>());
+        self.mapping.sync(byte_range, libc::MS_SYNC)
+    }
+
+    /// Like [`MemoryMapped::flush_range`] but returns without waiting for the writes to complete
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub fn flush_range_async<R: RangeBounds<usize>>(&self, range: R) -> Result<()> {
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, self.len(), std::mem::size_of::<T>());
+        self.mapping.sync(byte_range, libc::MS_ASYNC)
+    }
+
+    /// Applies `advice` to the pages covering the elements in `range` by calling [`libc::madvise`]
+    ///
+    /// Destructive advice (see [`Advice::is_destructive`]) is rejected with [`Error::DestructiveAdvice`],
+    /// use [`MemoryMapped::advise_range_unchecked`] instead.
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub fn advise_range<R: RangeBounds<usize>>(&self, range: R, advice: Advice) -> Result<()> {
+        reject_destructive(advice)?;
+        unsafe { self.advise_range_unchecked(range, advice) }
+    }
+
+    /// Applies `advice` to the pages covering the elements in `range` by calling [`libc::madvise`].
+    /// Note that the range is extended to whole pages, so neighbouring elements may be affected as well.
+    ///
+    /// # Safety
+    /// if `advice` is destructive the caller must ensure that all elements on the affected pages are still valid
+    /// values of `T` after the kernel discarded them
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub unsafe fn advise_range_unchecked<R: RangeBounds<usize>>(&self, range: R, advice: Advice) -> Result<()> {
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, self.len(), std::mem::size_of::<T>());
+        self.mapping.advise(byte_range, advice)
+    }
+
+    /// Changes the protection of the pages covering the elements in `range` by calling [`libc::mprotect`].
+    /// Note that the range is extended to whole pages, so neighbouring elements may be affected as well.
+    ///
+    /// # Safety
+    /// the caller must ensure that no element on the affected pages is accessed in a way `protection` does not permit
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub unsafe fn protect_range<R: RangeBounds<usize>>(&self, range: R, protection: Protection) -> Result<()> {
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, self.len(), std::mem::size_of::<T>());
+        self.mapping.protect_range(byte_range, protection.as_raw())
+    }
+}
+
+impl<T, M: Mode> MemoryMapped<[MaybeUninit<T>], M> {
     /// resizes `self` to `new_len` elements by calling [`libc::mremap`]
     ///
     /// # Safety
     /// if new_len > old_len the caller must ensure that the underlying file is large enough to support the size increase
-    pub unsafe fn resize_uninit(&mut self, new_len: usize) -> std::io::Result<()> {
+    pub unsafe fn resize_uninit(&mut self, new_len: usize) -> Result<()> {
         let new_byte_size = new_len * std::mem::size_of::<T>();
         self.mapping.byte_resize(new_byte_size)
     }
+
+    /// Like [`MemoryMapped::resize_uninit`] but never moves the mapping to a different address.
+    /// Fails with [`Error::WouldMove`] if the address range following the mapping is already in use.
+    ///
+    /// # Safety
+    /// see [`MemoryMapped::resize_uninit`]
+    pub unsafe fn try_resize_in_place_uninit(&mut self, new_len: usize) -> Result<()> {
+        let new_byte_size = new_len * std::mem::size_of::<T>();
+        self.mapping.byte_resize_in_place(new_byte_size)
+    }
 }
 
-impl<T> MemoryMapped<[T]> {
+impl<T, M: Mode> MemoryMapped<[T], M> {
     /// resizes `self` to `new_len` elements by calling [`libc::mremap`] without initializing the new elements
     /// in case of a length increase
     ///
     /// # Safety
     /// - if new_len > old_len the caller must ensure that the underlying file is large enough to support the size increase
     /// - the additional memory will not be initialized by this function but is assumed to be correctly initialized
-    pub unsafe fn resize_assume_init(&mut self, new_len: usize) -> std::io::Result<()> {
-        let uninit_self: &mut MemoryMapped<[MaybeUninit<T>]> = std::mem::transmute(self);
+    pub unsafe fn resize_assume_init(&mut self, new_len: usize) -> Result<()> {
+        let uninit_self: &mut MemoryMapped<[MaybeUninit<T>], M> = std::mem::transmute(self);
         uninit_self.resize_uninit(new_len)
     }
 
+    /// Like [`MemoryMapped::resize_assume_init`] but never moves the mapping to a different address.
+    /// Fails with [`Error::WouldMove`] if the address range following the mapping is already in use.
+    ///
+    /// # Safety
+    /// see [`MemoryMapped::resize_assume_init`]
+    pub unsafe fn try_resize_in_place_assume_init(&mut self, new_len: usize) -> Result<()> {
+        let uninit_self: &mut MemoryMapped<[MaybeUninit<T>], M> = std::mem::transmute(self);
+        uninit_self.try_resize_in_place_uninit(new_len)
+    }
+
+    /// Shrinks the capacity of this mapping to `new_len` by calling [`libc::mremap`]
+    pub fn shrink_to(&mut self, new_len: usize) -> Result<()> {
+        assert!(self.len() > new_len);
+
+        // SAFETY: size will shrink so no new possbily uninitialized memory can be produced by this call
+        // additionally the file already is known to have a size at least as large as this mapping
+        unsafe { self.resize_assume_init(new_len) }
+    }
+}
+
+impl<T, M: Writable> MemoryMapped<[T], M> {
     /// resizes `self` to `new_len` by calling [`libc::mremap`] overwriting the new elements by repeatedly calling `f`
     /// in case of a length increase
     ///
     /// # Safety
     /// if new_len > old_len the caller must ensure that the underlying file is large enough to support the size increase
-    pub unsafe fn resize_with<F>(&mut self, new_len: usize, mut f: F) -> std::io::Result<()>
+    pub unsafe fn resize_with<F>(&mut self, new_len: usize, f: F) -> Result<()>
+    where
+        F: FnMut() -> T,
+    {
+        self.resize_with_impl(new_len, false, f)
+    }
+
+    /// Like [`MemoryMapped::resize_with`] but never moves the mapping to a different address.
+    /// Fails with [`Error::WouldMove`] if the address range following the mapping is already in use.
+    ///
+    /// # Safety
+    /// see [`MemoryMapped::resize_with`]
+    pub unsafe fn try_resize_in_place_with<F>(&mut self, new_len: usize, f: F) -> Result<()>
+    where
+        F: FnMut() -> T,
+    {
+        self.resize_with_impl(new_len, true, f)
+    }
+
+    unsafe fn resize_with_impl<F>(&mut self, new_len: usize, in_place: bool, mut f: F) -> Result<()>
     where
         F: FnMut() -> T,
     {
         let old_len = self.len();
 
-        let uninit_self: &mut MemoryMapped<[MaybeUninit<T>]> = std::mem::transmute(self);
-        uninit_self.resize_uninit(new_len)?;
+        let uninit_self: &mut MemoryMapped<[MaybeUninit<T>], M> = std::mem::transmute(self);
+
+        if in_place {
+            uninit_self.try_resize_in_place_uninit(new_len)?;
+        } else {
+            uninit_self.resize_uninit(new_len)?;
+        }
 
         if new_len > old_len {
             let uninit_elems = &mut uninit_self.as_slice_mut()[old_len..];
@@ -200,29 +542,29 @@ impl<T> MemoryMapped<[T]> {
 
         Ok(())
     }
-
-    /// Shrinks the capacity of this mapping to `new_len` by calling [`libc::mremap`]
-    pub fn shrink_to(&mut self, new_len: usize) -> std::io::Result<()> {
-        assert!(self.len() > new_len);
-
-        // SAFETY: size will shrink so no new possbily uninitialized memory can be produced by this call
-        // additionally the file already is known to have a size at least as large as this mapping
-        unsafe { self.resize_assume_init(new_len) }
-    }
 }
 
-impl<T: Copy> MemoryMapped<[T]> {
+impl<T: Copy, M: Writable> MemoryMapped<[T], M> {
     /// resizes `self` to `new_len` by calling [`libc::mremap`] overwriting the new elements with `fill`
     /// in case of a length increase
     ///
     /// # Safety
     /// if new_len > old_len the caller must ensure that the underlying file is large enough to support the size increase
-    pub unsafe fn resize(&mut self, new_len: usize, fill: T) -> std::io::Result<()> {
+    pub unsafe fn resize(&mut self, new_len: usize, fill: T) -> Result<()> {
         self.resize_with(new_len, move || fill)
     }
+
+    /// Like [`MemoryMapped::resize`] but never moves the mapping to a different address.
+    /// Fails with [`Error::WouldMove`] if the address range following the mapping is already in use.
+    ///
+    /// # Safety
+    /// see [`MemoryMapped::resize`]
+    pub unsafe fn try_resize_in_place(&mut self, new_len: usize, fill: T) -> Result<()> {
+        self.try_resize_in_place_with(new_len, move || fill)
+    }
 }
 
-impl<T> Deref for MemoryMapped<T> {
+impl<T, M: Mode> Deref for MemoryMapped<T, M> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -230,13 +572,13 @@ impl<T> Deref for MemoryMapped<T> {
     }
 }
 
-impl<T> DerefMut for MemoryMapped<T> {
+impl<T, M: Writable> DerefMut for MemoryMapped<T, M> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { self.mapping.segment_ptr().cast().as_mut() }
     }
 }
 
-impl<T> Deref for MemoryMapped<[T]> {
+impl<T, M: Mode> Deref for MemoryMapped<[T], M> {
     type Target = [T];
 
     fn deref(&self) -> &Self::Target {
@@ -244,22 +586,22 @@ impl<T> Deref for MemoryMapped<[T]> {
     }
 }
 
-impl<T> DerefMut for MemoryMapped<[T]> {
+impl<T, M: Writable> DerefMut for MemoryMapped<[T], M> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.as_slice_mut()
     }
 }
 
-impl<T: Copy> IntoIterator for MemoryMapped<[T]> {
+impl<T: Copy, M: Mode> IntoIterator for MemoryMapped<[T], M> {
     type Item = T;
-    type IntoIter = IntoIter<T>;
+    type IntoIter = IntoIter<T, M>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIter { mmap: self, cur_ix: 0 }
     }
 }
 
-impl<T: Copy> Iterator for IntoIter<T> {
+impl<T: Copy, M: Mode> Iterator for IntoIter<T, M> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -276,8 +618,12 @@ impl<T: Copy> Iterator for IntoIter<T> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{page_size, MemoryMapped};
-    use std::fs::File;
+    use crate::{
+        huge_page_sizes, page_size, recv_fd, Advice, CodeBuffer, DualMappedCode, Error, HugePageSize, LayoutError,
+        LockError, MappedVec, MappingLayout, MemoryMapped, OpenOptions, Protection, ReadOnly, Reservation, Seals,
+        SharedMemory, SharedMutex, SysVSegment, ThreadShared, WaitOutcome,
+    };
+    use std::{fs::File, mem::MaybeUninit, ptr::NonNull};
 
     #[test]
     fn test_shared_resize() {
@@ -288,6 +634,7 @@ mod tests {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open("test.bin")
             .unwrap();
 
@@ -329,7 +676,7 @@ mod tests {
 
         m2.copy_within(..move_off, move_off);
 
-        m2 = unsafe {
+        let m3: MemoryMapped<[u32]> = unsafe {
             MemoryMapped::options()
                 .read(true)
                 .write(true)
@@ -339,10 +686,870 @@ mod tests {
                 .assume_init()
         };
 
+        assert_eq!(*m3.first().unwrap(), 0x33333333);
+        assert_eq!(*m3.last().unwrap(), 0x44444444);
+
         unsafe {
             m1.resize_assume_init(m1.len() * 2).unwrap();
         }
 
         *m1.last_mut().unwrap() = 0x99999999;
     }
+
+    #[test]
+    fn test_anonymous_resize() {
+        let mut m: MemoryMapped<[u64]> = MemoryMapped::anonymous_slice(16).unwrap().init();
+        assert!(m.iter().all(|&x| x == 0));
+
+        m.fill(7);
+
+        unsafe {
+            m.resize(page_size(), 9).unwrap();
+        }
+
+        assert!(m[..16].iter().all(|&x| x == 7));
+        assert!(m[16..].iter().all(|&x| x == 9));
+    }
+
+    #[test]
+    fn test_mapped_vec_persists_len() {
+        let path = std::env::temp_dir().join("memory_mapped_test_mapped_vec.bin");
+
+        let mut v: MappedVec<u64> = unsafe { MappedVec::create(&path).unwrap() };
+        let initial_capacity = v.capacity();
+
+        v.extend(0..initial_capacity as u64 + 1);
+        assert!(v.capacity() > initial_capacity);
+
+        assert_eq!(v.pop(), Some(initial_capacity as u64));
+        v.truncate(10);
+        v.shrink_to_fit().unwrap();
+        assert_eq!(v.capacity(), 10);
+        v.push(42).unwrap();
+        drop(v);
+
+        let v: MappedVec<u64> = unsafe { MappedVec::open(&path).unwrap() };
+        assert_eq!(v.len(), 11);
+        assert!(v[..10].iter().copied().eq(0..10));
+        assert_eq!(v[10], 42);
+    }
+
+    #[test]
+    fn test_flush_range() {
+        let path = std::env::temp_dir().join("memory_mapped_test_flush_range.bin");
+        let page_size = page_size();
+        let ints_per_page = page_size / std::mem::size_of::<u32>();
+
+        let f = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        f.set_len((page_size * 3) as u64).unwrap();
+
+        let mut m: MemoryMapped<[u32]> = unsafe {
+            MemoryMapped::options()
+                .read(true)
+                .write(true)
+                .offset(3)
+                .open_shared_slice_from_file(&f)
+                .unwrap()
+                .assume_init()
+        };
+
+        m[ints_per_page] = 0xdeadbeef;
+        m.flush_range(ints_per_page..=ints_per_page).unwrap();
+        m.flush_and_sync(&f).unwrap();
+
+        let contents = std::fs::read(&path).unwrap();
+        let ix = (ints_per_page + 3) * std::mem::size_of::<u32>();
+        assert_eq!(contents[ix..ix + 4], 0xdeadbeef_u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_advise() {
+        let m: MemoryMapped<[u8]> = unsafe {
+            MemoryMapped::options()
+                .read(true)
+                .write(true)
+                .len(page_size() * 4)
+                .advise(Advice::Sequential)
+                .open_anonymous_slice()
+                .unwrap()
+                .assume_init()
+        };
+
+        m.advise(Advice::Random).unwrap();
+        m.advise_range(1..page_size() + 1, Advice::WillNeed).unwrap();
+        assert!(m.advise(Advice::DontNeed).is_err());
+        unsafe { m.advise_range_unchecked(page_size().., Advice::DontNeed).unwrap() };
+    }
+
+    #[test]
+    fn test_mode_conversion() {
+        let path = std::env::temp_dir().join("memory_mapped_test_mode_conversion.bin");
+        std::fs::write(&path, 1u32.to_ne_bytes().repeat(16)).unwrap();
+
+        let m: MemoryMapped<[u32], ReadOnly> = MemoryMapped::open_slice(&path).unwrap().init();
+        assert!(m.iter().all(|&x| x == 1));
+
+        let mut m = m.into_read_write().unwrap();
+        m[0] = 2;
+
+        let m = m.into_read_only().unwrap();
+        assert_eq!(m[0], 2);
+
+        // the mapping is private, so the write must not reach the file
+        assert_eq!(std::fs::read(&path).unwrap()[..4], 1u32.to_ne_bytes());
+
+        // a shared mapping of a file opened read-only cannot be made writable, the mapping is handed back
+        let f = File::open(&path).unwrap();
+        let m: MemoryMapped<[u32], ReadOnly> = unsafe {
+            OpenOptions::<[u32], ReadOnly>::new()
+                .open_shared_slice_from_file(&f)
+                .unwrap()
+        }
+        .init();
+        let Err(err) = m.into_read_write() else {
+            panic!("a read-only file must not be mapped writable");
+        };
+        assert_eq!(
+            err.error().os_error().and_then(|e| e.raw_os_error()),
+            Some(libc::EACCES)
+        );
+
+        let m = err.into_inner();
+        assert!(m.iter().all(|&x| x == 1));
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut m: MemoryMapped<[u64]> = MemoryMapped::anonymous_slice(page_size()).unwrap().init();
+
+        for (ix, x) in m.iter_mut().enumerate() {
+            *x = ix as u64;
+        }
+
+        unsafe { m.protect_range(..1, Protection::READ).unwrap() };
+        unsafe { m.protect(Protection::READ | Protection::WRITE).unwrap() };
+        m[0] = 1;
+
+        let m = m.freeze().unwrap();
+        assert_eq!(m[0], 1);
+        assert_eq!(m[page_size() - 1], page_size() as u64 - 1);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_code_buffer() {
+        // lea eax, [rdi + 1]; ret
+        const INC: [u8; 4] = [0x8d, 0x47, 0x01, 0xc3];
+        // lea eax, [rdi - 1]; ret
+        const DEC: [u8; 4] = [0x8d, 0x47, 0xff, 0xc3];
+
+        let mut buf = CodeBuffer::new(page_size()).unwrap();
+        buf[..4].copy_from_slice(&INC);
+
+        let code = buf.finalize().unwrap();
+        let inc: extern "C" fn(i32) -> i32 = unsafe { code.function(0) };
+        assert_eq!(inc(41), 42);
+
+        let mut dual = DualMappedCode::new(page_size()).unwrap();
+        dual.writable()[..4].copy_from_slice(&INC);
+        let f: extern "C" fn(i32) -> i32 = unsafe { dual.function(0) };
+        assert_eq!(f(1), 2);
+
+        dual.writable()[..4].copy_from_slice(&DEC);
+        assert_eq!(f(1), 0);
+    }
+
+    #[test]
+    fn test_layout_validation() {
+        let path = std::env::temp_dir().join("memory_mapped_test_layout_validation.bin");
+        std::fs::write(&path, [0u8; 64]).unwrap();
+
+        let layout_err = |e| match e {
+            Error::InvalidLayout { source, .. } => source,
+            e => panic!("unexpected error {e}"),
+        };
+
+        let e = MemoryMapped::<[u32], ReadOnly>::options()
+            .read(true)
+            .byte_offset(128)
+            .open_slice(&path)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            e,
+            Error::FileTooSmall { path: Some(_), byte_offset: 128, byte_len: 0, file_len: 64 }
+        ));
+
+        let e = MemoryMapped::<[u32], ReadOnly>::options()
+            .read(true)
+            .byte_offset(2)
+            .open_slice(&path)
+            .err()
+            .unwrap();
+        assert_eq!(
+            layout_err(e),
+            LayoutError::MisalignedOffset { byte_offset: 2, align: 4 }
+        );
+
+        let e = MemoryMapped::<[u32], ReadOnly>::options()
+            .read(true)
+            .byte_offset(4)
+            .byte_len(6)
+            .open_slice(&path)
+            .err()
+            .unwrap();
+        assert_eq!(
+            layout_err(e),
+            LayoutError::LengthNotMultipleOfSize { byte_len: 6, elem_size: 4 }
+        );
+
+        let e = MemoryMapped::<[u64; 4], ReadOnly>::options()
+            .read(true)
+            .byte_offset(40)
+            .open(&path)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            e,
+            Error::FileTooSmall { byte_offset: 40, byte_len: 32, file_len: 64, .. }
+        ));
+
+        let e = MemoryMapped::<[u32], ReadOnly>::options()
+            .read(true)
+            .byte_offset(16)
+            .byte_len(usize::MAX - 3)
+            .open_slice(&path)
+            .err()
+            .unwrap();
+        assert!(matches!(e, Error::FileTooSmall { byte_offset: 16, file_len: 64, .. }));
+
+        let m = MemoryMapped::<[u32; 4], ReadOnly>::options()
+            .read(true)
+            .byte_offset(16)
+            .open(&path)
+            .unwrap();
+        assert_eq!(m.segment_byte_len(), 16);
+    }
+
+    #[test]
+    fn test_huge_pages() {
+        // kernels without hugetlbfs support and some containers do not expose huge pages at all
+        let (Ok(sizes), Ok(huge_page_size)) = (huge_page_sizes(), HugePageSize::Default.byte_size()) else {
+            return;
+        };
+        assert!(sizes.iter().all(|&size| size > page_size()));
+        assert!(sizes.contains(&huge_page_size));
+
+        let res = MemoryMapped::<[u8]>::options()
+            .read(true)
+            .write(true)
+            .len(page_size())
+            .huge_pages(HugePageSize::Default)
+            .open_anonymous_slice();
+
+        match res {
+            Ok(m) => {
+                let mut m = m.init();
+                m.fill(1);
+                assert_eq!(m.len(), page_size());
+                m.advise(Advice::WillNeed).unwrap();
+            }
+            // none may be reserved, or the free ones may be held back by reservations or cgroup limits
+            Err(Error::HugePagesUnavailable { page_size, byte_len, .. }) => {
+                assert_eq!((page_size, byte_len), (huge_page_size, huge_page_size));
+            }
+            Err(e) => panic!("unexpected error {e}"),
+        }
+    }
+
+    #[test]
+    fn test_prefault_and_lock() {
+        let path = std::env::temp_dir().join("memory_mapped_test_prefault.bin");
+        std::fs::write(&path, 3u16.to_ne_bytes().repeat(page_size() * 2)).unwrap();
+
+        let m = MemoryMapped::<[u16], ReadOnly>::options()
+            .read(true)
+            .populate(true)
+            .open_slice(&path)
+            .unwrap()
+            .init();
+
+        m.prefault().unwrap();
+        m.prefault_parallel(3).unwrap();
+        assert!(m.iter().all(|&x| x == 3));
+
+        m.mlock_on_fault().unwrap();
+        m.munlock().unwrap();
+        m.mlock().unwrap();
+        m.munlock().unwrap();
+    }
+
+    #[test]
+    fn test_mmap_flags() {
+        // mapping at a fixed address is only free of races with other threads in a single threaded child process
+        match unsafe { libc::fork() } {
+            0 => {
+                let res = std::panic::catch_unwind(|| {
+                    let m = MemoryMapped::<[u8]>::anonymous_slice(page_size() * 2).unwrap().init();
+                    let addr = NonNull::from(&m[page_size()]).cast::<()>();
+                    m.close().unwrap();
+
+                    let m = MemoryMapped::<[u8]>::options()
+                        .read(true)
+                        .write(true)
+                        .len(page_size())
+                        .no_reserve(true)
+                        .fixed_noreplace(addr)
+                        .open_anonymous_slice()
+                        .unwrap()
+                        .init();
+                    assert_eq!(m.as_ptr(), addr.as_ptr() as *const u8);
+
+                    let e = MemoryMapped::<[u8]>::options()
+                        .len(page_size())
+                        .fixed_noreplace(addr)
+                        .open_anonymous_slice()
+                        .err()
+                        .unwrap();
+                    assert_eq!(e.os_error().unwrap().raw_os_error(), Some(libc::EEXIST));
+
+                    let e = MemoryMapped::<[u8]>::options()
+                        .len(page_size())
+                        .fixed_noreplace(NonNull::from(&m[1]).cast())
+                        .open_anonymous_slice()
+                        .err()
+                        .unwrap();
+                    assert!(matches!(e, Error::InvalidFlags { .. }));
+                });
+                unsafe { libc::_exit(res.is_err() as libc::c_int) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+            }
+        }
+
+        let e = MemoryMapped::<[u8]>::options()
+            .len(page_size())
+            .shared_validate(true)
+            .open_anonymous_slice()
+            .err()
+            .unwrap();
+        assert!(matches!(e, Error::InvalidFlags { .. }));
+
+        let e = MemoryMapped::<[u8]>::options()
+            .len(page_size())
+            .grows_down(true)
+            .open_shared_anonymous_slice()
+            .err()
+            .unwrap();
+        assert!(matches!(e, Error::InvalidFlags { .. }));
+
+        let path = std::env::temp_dir().join("memory_mapped_test_mmap_flags.bin");
+        std::fs::write(&path, [0u8; 64]).unwrap();
+
+        let e = MemoryMapped::<[u8], ReadOnly>::options()
+            .read(true)
+            .uninitialized(true)
+            .open_slice(&path)
+            .err()
+            .unwrap();
+        assert!(matches!(e, Error::InvalidFlags { .. }));
+
+        let m = unsafe {
+            MemoryMapped::<[u8], ReadOnly>::options()
+                .read(true)
+                .shared_validate(true)
+                .stack(true)
+                .open_shared_slice(&path)
+                .unwrap()
+                .init()
+        };
+        assert_eq!(m.len(), 64);
+    }
+
+    #[test]
+    fn test_reservation() {
+        let mut r: Reservation<u32> = Reservation::new(1 << 32).unwrap();
+        assert!(r.is_empty());
+        assert_eq!(r.capacity(), 1 << 30);
+
+        r.commit(10).unwrap();
+        r.fill(1);
+
+        let base = r.as_ptr();
+        let first = &r[0];
+        r.commit(page_size() * 4).unwrap();
+        assert_eq!(*first, 1);
+        assert_eq!(r.as_ptr(), base);
+        assert!(r[10..].iter().all(|&x| x == 0));
+
+        r.fill(2);
+        r.decommit(5..page_size() * 2).unwrap();
+        assert!(r[..5].iter().all(|&x| x == 2));
+        assert!(r[5..page_size() * 2].iter().all(|&x| x == 0));
+        assert!(r[page_size() * 2..].iter().all(|&x| x == 2));
+
+        r.decommit(7..).unwrap();
+        assert_eq!(r.len(), 7);
+        r.commit(page_size()).unwrap();
+        assert!(r[..5].iter().all(|&x| x == 2));
+        assert!(r[5..].iter().all(|&x| x == 0));
+
+        assert!(matches!(
+            r.commit(r.capacity() + 1),
+            Err(Error::ReservationExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resize_in_place() {
+        fn block_next_page(m: &[u8]) -> crate::Result<MemoryMapped<[MaybeUninit<u8>]>> {
+            let addr = NonNull::new(m.as_ptr_range().end as *mut ()).unwrap();
+            MemoryMapped::options()
+                .len(page_size())
+                .fixed_noreplace(addr)
+                .open_anonymous_slice()
+        }
+
+        let mut m: MemoryMapped<[u8]> = MemoryMapped::anonymous_slice(page_size() * 2).unwrap().init();
+        m.fill(1);
+
+        let base = m.as_ptr();
+        unsafe { m.try_resize_in_place(page_size(), 0).unwrap() };
+        assert_eq!(m.as_ptr(), base);
+
+        // if the page is already in use by another mapping it blocks the resize as well
+        let _blocker = block_next_page(&m);
+
+        let e = unsafe { m.try_resize_in_place(page_size() * 2, 2).err().unwrap() };
+        assert!(matches!(e, Error::WouldMove { .. }));
+        assert_eq!(m.len(), page_size());
+        assert!(m.iter().all(|&x| x == 1));
+
+        let mut m: MemoryMapped<[u8]> = MemoryMapped::options()
+            .read(true)
+            .write(true)
+            .len(page_size())
+            .relocatable(false)
+            .open_anonymous_slice()
+            .unwrap()
+            .init();
+
+        let _blocker = block_next_page(&m);
+
+        let e = unsafe { m.resize(page_size() * 2, 0).err().unwrap() };
+        assert!(matches!(e, Error::WouldMove { .. }));
+    }
+
+    #[test]
+    fn test_guard_pages() {
+        fn is_guard(addr: *const u8) -> bool {
+            let addr = addr as usize;
+
+            std::fs::read_to_string("/proc/self/maps").unwrap().lines().any(|line| {
+                let mut fields = line.split_whitespace();
+                let (start, end) = fields.next().unwrap().split_once('-').unwrap();
+                let range = usize::from_str_radix(start, 16).unwrap()..usize::from_str_radix(end, 16).unwrap();
+
+                range.contains(&addr) && fields.next() == Some("---p")
+            })
+        }
+
+        let mut m: MemoryMapped<[u8]> = MemoryMapped::options()
+            .read(true)
+            .write(true)
+            .len(page_size() * 2)
+            .guard_pages(1, 2)
+            .open_anonymous_slice()
+            .unwrap()
+            .init();
+
+        let check_guards = |m: &[u8]| {
+            let range = m.as_ptr_range();
+            assert!(is_guard(range.start.wrapping_sub(1)));
+            assert!(is_guard(range.end));
+            assert!(is_guard(range.end.wrapping_add(page_size() * 2 - 1)));
+            assert!(!is_guard(range.end.wrapping_sub(1)));
+        };
+
+        check_guards(&m);
+
+        unsafe { m.resize(page_size() * 5, 1).unwrap() };
+        check_guards(&m);
+        assert!(m[page_size() * 2..].iter().all(|&x| x == 1));
+
+        m.shrink_to(page_size()).unwrap();
+        check_guards(&m);
+        m.fill(2);
+
+        m.close().unwrap();
+    }
+
+    #[test]
+    fn test_memfd_seals() {
+        let mut m: MemoryMapped<[u32]> = MemoryMapped::memfd("test_memfd_seals", 256).unwrap().init();
+        m.fill(5);
+
+        m.seal(Seals::SHRINK | Seals::GROW).unwrap();
+
+        let e = MemoryMapped::<[u32]>::memfd("test_memfd_overflow", usize::MAX / 2)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            e,
+            Error::InvalidLayout { source: LayoutError::LengthOverflow { elem_size: 4, .. }, .. }
+        ));
+
+        let fd = m.backing_fd().unwrap().try_clone_to_owned().unwrap();
+        assert_eq!(Seals::of(&fd).unwrap(), Seals::SHRINK | Seals::GROW);
+        assert!(File::from(fd).set_len(0).is_err());
+
+        let e = MemoryMapped::<[u32], ReadOnly>::options()
+            .open_sealed_slice_from_fd(&m.backing_fd().unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(e, Error::MissingSeals { .. }));
+
+        // a write seal cannot be placed while the writable mapping exists
+        assert!(m.seal(Seals::WRITE).is_err());
+
+        let fd = m.into_backing_fd().unwrap();
+        Seals::WRITE.add_to(&fd).unwrap();
+
+        let m = MemoryMapped::<[u32], ReadOnly>::options()
+            .open_sealed_slice_from_fd(&fd)
+            .unwrap()
+            .init();
+        assert_eq!(m.len(), 256);
+        assert!(m.iter().all(|&x| x == 5));
+    }
+
+    #[test]
+    fn test_shared_memory() {
+        let name = format!("memory_mapped_test_{}", std::process::id());
+
+        let shm = SharedMemory::create_with_mode(&name, 4096, 0o640).unwrap();
+        assert_eq!(shm.byte_len().unwrap(), 4096);
+        assert!(SharedMemory::create(&name, 4096).is_err());
+
+        let mode = std::fs::metadata(format!("/dev/shm/{name}")).unwrap().permissions();
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777, 0o640);
+
+        let mut m: MemoryMapped<[u32]> = unsafe { shm.map_slice().unwrap().init() };
+        assert_eq!(m.len(), 1024);
+        m[3] = 7;
+
+        let other = SharedMemory::open_read_only(&name).unwrap();
+        let m2 = unsafe {
+            MemoryMapped::<[u32], ReadOnly>::options()
+                .read(true)
+                .open_shared_slice_from_fd(&other)
+                .unwrap()
+                .init()
+        };
+        assert_eq!(m2[3], 7);
+
+        drop(shm);
+        assert!(SharedMemory::open(&name).is_err());
+        assert_eq!(m2[3], 7);
+    }
+
+    #[test]
+    fn test_sysv_shared_memory() {
+        let segment = SysVSegment::create(libc::IPC_PRIVATE, page_size(), 0o600).unwrap();
+
+        let mut m: MemoryMapped<[u64]> = unsafe { MemoryMapped::attach_sysv_slice(&segment).unwrap().init() };
+        assert_eq!(m.len(), page_size() / 8);
+        m[1] = 11;
+
+        let stat = segment.stat().unwrap();
+        assert_eq!(stat.byte_len, page_size());
+        assert_eq!(stat.mode & 0o777, 0o600);
+        assert_eq!(stat.attach_count, 1);
+
+        let m2: MemoryMapped<[u64; 2], ReadOnly> = unsafe { MemoryMapped::attach_sysv(&segment).unwrap().init() };
+        assert_eq!(m2[1], 11);
+        assert_eq!(segment.stat().unwrap().attach_count, 2);
+        let e = unsafe { m.resize(1, 0) }.err().unwrap();
+        assert!(matches!(e, Error::Remap { path: None, file_byte_offset: 0, .. }));
+
+        drop(m2);
+        assert_eq!(segment.stat().unwrap().attach_count, 1);
+
+        segment.remove().unwrap();
+        assert_eq!(m[1], 11);
+
+        m.close().unwrap();
+        assert!(segment.stat().is_err());
+    }
+
+    #[test]
+    fn test_fd_passing() {
+        use std::os::unix::net::UnixStream;
+
+        let (tx, rx) = UnixStream::pair().unwrap();
+
+        let mut m: MemoryMapped<[u32]> = MemoryMapped::memfd("fd_passing", 1024).unwrap().init();
+        m[3] = 42;
+        m.send_to(&tx).unwrap();
+
+        let mut received: MemoryMapped<[u32]> = unsafe { MemoryMapped::recv_from(&rx).unwrap().init() };
+        assert_eq!(received.len(), 1024);
+        assert_eq!(received[3], 42);
+
+        received[4] = 7;
+        assert_eq!(m[4], 7);
+
+        received.send_to(&tx).unwrap();
+        assert!(matches!(
+            unsafe { MemoryMapped::<[i32]>::recv_from(&rx) },
+            Err(Error::LayoutMismatch { received: MappingLayout { elem_size: 4, .. }, .. })
+        ));
+
+        // a message without a descriptor is consumed completely, so the following one is received intact
+        std::io::Write::write_all(&mut &tx, &[0; 40]).unwrap();
+        assert!(matches!(recv_fd(&rx), Err(Error::Socket { call: "recvmsg", .. })));
+
+        let shm_name = format!("memory_mapped_fd_passing_{}", std::process::id());
+        let shm = SharedMemory::create(&shm_name, page_size()).unwrap();
+        let shm_mapped: MemoryMapped<[u64]> = unsafe { shm.map_slice().unwrap().init() };
+        shm_mapped.send_to(&tx).unwrap();
+
+        let shm_received: MemoryMapped<[u64], ReadOnly> = unsafe { MemoryMapped::recv_from(&rx).unwrap().init() };
+        assert_eq!(shm_received.len(), page_size() / 8);
+
+        let anonymous: MemoryMapped<[u32]> = MemoryMapped::anonymous_slice(16).unwrap().init();
+        assert!(anonymous.send_to(&tx).is_err());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<MemoryMapped<[u32]>>();
+        assert_send_sync::<MemoryMapped<[MaybeUninit<u64>], ReadOnly>>();
+        assert_send_sync::<MemoryMapped<std::sync::atomic::AtomicU32>>();
+        assert_send_sync::<ThreadShared<[std::cell::Cell<u32>]>>();
+
+        let mapped: MemoryMapped<[std::cell::Cell<u32>]> =
+            unsafe { MemoryMapped::anonymous_slice(4).unwrap().assume_init() };
+        let shared = unsafe { mapped.into_thread_shared() };
+
+        std::thread::scope(|s| {
+            for i in 0..4 {
+                let shared = &shared;
+                s.spawn(move || shared[i].set(i as u32 + 1));
+            }
+        });
+
+        let mapped = shared.into_inner();
+        assert_eq!(mapped.iter().map(|c| c.get()).collect::<Vec<_>>(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_shared_view() {
+        use std::sync::atomic::Ordering;
+
+        #[repr(C)]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Entry {
+            seq: u32,
+            value: u32,
+        }
+
+        unsafe impl crate::MapSafe for Entry {}
+
+        let path = std::env::temp_dir().join("memory_mapped_test_shared_view.bin");
+        File::create(&path).unwrap().set_len(4 * 8).unwrap();
+
+        let mut writer = unsafe {
+            OpenOptions::<[Entry]>::new()
+                .read(true)
+                .write(true)
+                .open_shared_slice(&path)
+                .unwrap()
+        };
+        let reader = unsafe {
+            OpenOptions::<[Entry], ReadOnly>::new()
+                .read(true)
+                .open_shared_slice(&path)
+                .unwrap()
+        };
+
+        let w = writer.shared_view_mut();
+        let r = reader.shared_view();
+        assert_eq!(r.len(), 4);
+
+        w.write(1, Entry { seq: 1, value: 10 });
+        assert_eq!(r.read(1), Entry { seq: 1, value: 10 });
+
+        w.store::<u32>(2 * 8 + std::mem::offset_of!(Entry, value), 7, Ordering::Release);
+        assert_eq!(r.load_relaxed::<u32>(2 * 8 + 4), 7);
+        std::sync::atomic::fence(Ordering::Acquire);
+        assert_eq!(r.load_relaxed::<u32>(8), 1);
+        assert_eq!(w.load::<u32>(2 * 8 + 4, Ordering::Acquire), 7);
+
+        w.copy_from(2.., &[Entry { seq: 3, value: 30 }, Entry { seq: 4, value: 40 }]);
+
+        let mut buf = [Entry { seq: 0, value: 0 }; 3];
+        r.copy_to(1..4, &mut buf);
+        assert_eq!(buf.map(|e| e.seq), [1, 3, 4]);
+
+        assert!(std::panic::catch_unwind(|| r.load_relaxed::<u64>(4)).is_err());
+        assert!(std::panic::catch_unwind(|| r.read(4)).is_err());
+        assert!(std::panic::catch_unwind(|| r.load_relaxed::<u32>(usize::MAX - 3)).is_err());
+
+        drop(reader);
+        drop(writer);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_counters() {
+        use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+        let path = std::env::temp_dir().join("memory_mapped_test_atomic_counters.bin");
+        File::create(&path).unwrap().set_len(8 * 8).unwrap();
+
+        let mut opts = OpenOptions::<[AtomicU64]>::new();
+        opts.read(true).write(true);
+
+        let a: MemoryMapped<[AtomicU64]> = unsafe { opts.open_shared_slice(&path).unwrap() }.init();
+        let b: MemoryMapped<[AtomicU64]> = unsafe { opts.open_shared_slice(&path).unwrap() }.init();
+        assert_eq!(a.len(), 8);
+
+        std::thread::scope(|s| {
+            for m in [&a, &b] {
+                s.spawn(move || {
+                    for _ in 0..1000 {
+                        m.fetch_add_at(3, 1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+
+        b.store_at(7, 5, Ordering::Release);
+        assert_eq!(a.fetch_sub_at(7, 2, Ordering::AcqRel), 5);
+        assert_eq!(b.load_at(3, Ordering::Acquire), 2000);
+        assert_eq!(a.snapshot(), [0, 0, 0, 2000, 0, 0, 0, 3]);
+
+        let misaligned = unsafe { opts.byte_offset(4).open_shared_slice(&path) };
+        assert!(matches!(
+            misaligned,
+            Err(Error::InvalidLayout { source: LayoutError::MisalignedOffset { align: 8, .. }, .. })
+        ));
+
+        let ro: MemoryMapped<[AtomicU32], ReadOnly> = unsafe {
+            OpenOptions::<[AtomicU32], ReadOnly>::new()
+                .read(true)
+                .open_shared_slice(&path)
+                .unwrap()
+        }
+        .init();
+        assert_eq!(ro.load_relaxed_at(6), 2000);
+        std::sync::atomic::fence(Ordering::Acquire);
+        assert_eq!(ro.snapshot_relaxed()[7], 0);
+
+        drop((a, b, ro));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_futex() {
+        use std::{
+            sync::atomic::{AtomicU32, Ordering},
+            time::Duration,
+        };
+
+        let m: MemoryMapped<[AtomicU32]> = MemoryMapped::anonymous_shared_slice(2).unwrap().init();
+
+        assert_eq!(m.wait_at(0, 1, None).unwrap(), WaitOutcome::ValueMismatch);
+        assert_eq!(
+            m.wait_at(1, 0, Some(Duration::from_millis(10))).unwrap(),
+            WaitOutcome::TimedOut
+        );
+        assert_eq!(m.wake_at(0, 1).unwrap(), 0);
+
+        std::thread::scope(|s| {
+            let waiter = s.spawn(|| {
+                while m[0].load(Ordering::Acquire) == 0 {
+                    m.wait_at(0, 0, None).unwrap();
+                }
+            });
+
+            std::thread::sleep(Duration::from_millis(10));
+            m[0].store(1, Ordering::Release);
+            m.wake_at(0, u32::MAX).unwrap();
+            waiter.join().unwrap();
+        });
+
+        let outside = AtomicU32::new(0);
+        assert!(matches!(
+            m.wake(&outside, 1),
+            Err(Error::AddressOutOfBounds { byte_len: 8, .. })
+        ));
+        assert!(matches!(
+            m.wait(&outside, 0, None),
+            Err(Error::AddressOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_shared_mutex() {
+        let m: MemoryMapped<MaybeUninit<SharedMutex<[u64; 2]>>> = MemoryMapped::anonymous_shared().unwrap();
+        let m = m.init_shared_mutex([0; 2]).unwrap();
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        let mut guard = m.lock().unwrap();
+                        guard[0] += 1;
+                        guard[1] = guard[0];
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*m.lock().unwrap(), [4000, 4000]);
+
+        let die_holding_lock = || match unsafe { libc::fork() } {
+            0 => {
+                let mut guard = m.lock().unwrap();
+                guard[0] = 1;
+                std::mem::forget(guard);
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                assert!(pid > 0);
+                assert_eq!(unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) }, pid);
+            }
+        };
+
+        die_holding_lock();
+
+        match m.try_lock() {
+            Err(LockError::OwnerDied(guard)) => {
+                assert_eq!(guard[0], 1);
+                guard.mark_consistent().unwrap();
+            }
+            res => panic!("expected the owner to be dead: {:?}", res.map(|g| g.is_some())),
+        }
+
+        assert!(m.lock().unwrap().mark_consistent().is_err());
+
+        die_holding_lock();
+
+        assert!(matches!(m.lock(), Err(LockError::OwnerDied(_))));
+        assert!(matches!(m.lock(), Err(LockError::Poisoned)));
+    }
 }
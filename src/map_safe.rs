@@ -0,0 +1,67 @@
+/// Types for which every bit pattern of the right size is a valid value
+///
+/// Mappings of such types can be initialized safely with [`crate::MemoryMapped::init`]
+/// instead of [`crate::MemoryMapped::assume_init`], since whatever the mapped memory contains
+/// is a valid `T`.
+///
+/// # Safety
+/// implementors must ensure that
+/// - every bit pattern of `size_of::<Self>()` bytes is a valid value of `Self`, in particular `Self` must not contain padding
+/// - `Self` does not contain references, pointers or other types with validity invariants
+/// - `Self` does not have a [`Drop`] implementation that relies on its contents
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::{MapSafe, MemoryMapped};
+///
+/// #[repr(C)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// unsafe impl MapSafe for Point {}
+///
+/// let points = MemoryMapped::<[Point]>::open_slice("points.bin").unwrap().init();
+/// ```
+pub unsafe trait MapSafe {}
+
+macro_rules! impl_map_safe {
+    ($($t:ty),* $(,)?) => {
+        $(unsafe impl MapSafe for $t {})*
+    };
+}
+
+impl_map_safe!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    ()
+);
+
+// atomic integers have the same in-memory representation as the underlying integers
+impl_map_safe!(
+    std::sync::atomic::AtomicU8,
+    std::sync::atomic::AtomicU16,
+    std::sync::atomic::AtomicU32,
+    std::sync::atomic::AtomicU64,
+    std::sync::atomic::AtomicUsize,
+    std::sync::atomic::AtomicI8,
+    std::sync::atomic::AtomicI16,
+    std::sync::atomic::AtomicI32,
+    std::sync::atomic::AtomicI64,
+    std::sync::atomic::AtomicIsize,
+);
+
+unsafe impl<T: MapSafe, const N: usize> MapSafe for [T; N] {}
@@ -0,0 +1,326 @@
+use crate::{error::last_os_error, page_size, raw_memory_mapping, Error, OpenOptions, RawMemoryMapping, Result};
+use std::{
+    fs::File,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+/// Size of the header at the start of the file that stores the logical length of the vector
+const HEADER_BYTE_LEN: usize = std::mem::size_of::<u64>();
+
+/// A growable, file-backed vector
+///
+/// The backing file is grown geometrically before the mapping is resized, so unlike
+/// [`crate::MemoryMapped::resize_with`] no manual `set_len` on the file is required.
+/// The logical length is stored in a small header at the start of the file so that reopening
+/// the file yields the same number of elements.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::MappedVec;
+///
+/// let mut v: MappedVec<u32> = unsafe { MappedVec::create("some_vec.bin").unwrap() };
+/// v.extend([1, 2, 3]);
+/// v.push(4).unwrap();
+/// drop(v);
+///
+/// let v: MappedVec<u32> = unsafe { MappedVec::open("some_vec.bin").unwrap() };
+/// assert_eq!(&v[..], &[1, 2, 3, 4]);
+/// ```
+pub struct MappedVec<T: Copy> {
+    file: File,
+    mapping: RawMemoryMapping,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Copy + Sync> Sync for MappedVec<T> {}
+unsafe impl<T: Copy + Send> Send for MappedVec<T> {}
+
+impl<T: Copy> Drop for MappedVec<T> {
+    fn drop(&mut self) {
+        let _ = self.mapping.close();
+    }
+}
+
+impl<T: Copy> MappedVec<T> {
+    /// Byte offset of the first element in the file
+    const DATA_BYTE_OFFSET: usize = HEADER_BYTE_LEN.next_multiple_of(std::mem::align_of::<T>());
+
+    /// Creates a new, empty vector backed by the file at `path`.
+    /// This function will create a file if it does not exist and truncate it if it does.
+    ///
+    /// # Safety
+    /// the file is mapped as shared, so the caller must ensure that it is not mapped anywhere else,
+    /// e.g. by another [`MappedVec`], for as long as the vector exists
+    pub unsafe fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_capacity(path, 0)
+    }
+
+    /// Creates a new, empty vector with space for at least `capacity` elements backed by the file at `path`.
+    /// This function will create a file if it does not exist and truncate it if it does.
+    ///
+    /// # Safety
+    /// see [`MappedVec::create`]
+    pub unsafe fn with_capacity<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|source| Error::Open { path: Some(path.to_owned()), source })?;
+
+        let byte_len = Self::file_byte_len_for(capacity).max(page_size());
+        allocate(&file, 0, byte_len).map_err(|e| e.with_path(path))?;
+
+        Self::from_file(file, byte_len, path).map_err(|e| e.with_path(path))
+    }
+
+    /// Opens an existing vector previously created by [`MappedVec::create`] or [`MappedVec::with_capacity`]
+    ///
+    /// # Safety
+    /// - the caller must ensure that the file contains properly initialized values of type `T`
+    /// - see [`MappedVec::create`]
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let open_err = |source| Error::Open { path: Some(path.to_owned()), source };
+
+        let file = File::options().read(true).write(true).open(path).map_err(open_err)?;
+        let file_len = file.metadata().map_err(open_err)?.len() as usize;
+
+        if file_len < Self::DATA_BYTE_OFFSET {
+            return Err(Error::FileTooSmall {
+                path: Some(path.to_owned()),
+                byte_offset: 0,
+                byte_len: Self::DATA_BYTE_OFFSET,
+                file_len,
+            });
+        }
+
+        let v = Self::from_file(file, file_len, path).map_err(|e| e.with_path(path))?;
+
+        if v.len() > v.capacity() {
+            return Err(Error::FileTooSmall {
+                path: Some(path.to_owned()),
+                byte_offset: Self::DATA_BYTE_OFFSET,
+                byte_len: v.len() * std::mem::size_of::<T>(),
+                file_len,
+            });
+        }
+
+        Ok(v)
+    }
+
+    fn from_file(file: File, byte_len: usize, path: &Path) -> Result<Self> {
+        assert!(std::mem::size_of::<T>() != 0, "zero sized types are not supported");
+
+        let mut opts = OpenOptions::<[T]>::new();
+        opts.read(true).write(true).byte_len(byte_len);
+
+        let mut mapping = RawMemoryMapping::open(file.as_raw_fd(), &opts.with_shared(true))?;
+        mapping.set_path(path);
+
+        Ok(MappedVec { file, mapping, _marker: PhantomData })
+    }
+
+    fn file_byte_len_for(capacity: usize) -> usize {
+        Self::DATA_BYTE_OFFSET + capacity * std::mem::size_of::<T>()
+    }
+
+    fn header(&self) -> *mut u64 {
+        self.mapping.segment_ptr().as_ptr() as *mut u64
+    }
+
+    fn data_ptr(&self) -> *mut T {
+        unsafe { self.mapping.segment_ptr().as_ptr().byte_add(Self::DATA_BYTE_OFFSET) as *mut T }
+    }
+
+    /// Returns the number of elements in the vector
+    pub fn len(&self) -> usize {
+        unsafe { self.header().read() as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets the logical length of the vector and persists it in the file header
+    ///
+    /// # Safety
+    /// - `new_len` must be less than or equal to [`MappedVec::capacity`]
+    /// - the elements at `old_len..new_len` must be initialized
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity());
+        self.header().write(new_len as u64);
+    }
+
+    /// Returns the number of elements the vector can hold without growing the backing file
+    pub fn capacity(&self) -> usize {
+        (self.mapping.segment_byte_len() - Self::DATA_BYTE_OFFSET) / std::mem::size_of::<T>()
+    }
+
+    /// Returns a reference to the backing file
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { &*std::ptr::slice_from_raw_parts(self.data_ptr(), self.len()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { &mut *std::ptr::slice_from_raw_parts_mut(self.data_ptr(), self.len()) }
+    }
+
+    /// Appends an element to the back of the vector, growing the backing file if necessary
+    pub fn push(&mut self, value: T) -> Result<()> {
+        let len = self.len();
+        self.reserve(1)?;
+
+        unsafe {
+            self.data_ptr().add(len).write(value);
+            self.set_len(len + 1);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the last element of the vector and returns it, or `None` if it is empty
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len();
+
+        if len == 0 {
+            return None;
+        }
+
+        unsafe {
+            self.set_len(len - 1);
+            Some(self.data_ptr().add(len - 1).read())
+        }
+    }
+
+    /// Shortens the vector to `len` elements.
+    /// Has no effect if `len` is greater than or equal to the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            unsafe { self.set_len(len) }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    /// The backing file is grown geometrically with [`libc::fallocate`] before the mapping is resized with [`libc::mremap`].
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        let required = self.len().checked_add(additional).expect("capacity overflow");
+
+        if required <= self.capacity() {
+            return Ok(());
+        }
+
+        let new_capacity = required.max(self.capacity() * 2);
+        self.resize_capacity(new_capacity)
+    }
+
+    /// Shrinks the capacity of the vector, and the size of the backing file, as much as possible
+    pub fn shrink_to_fit(&mut self) -> Result<()> {
+        if self.capacity() > self.len() {
+            self.resize_capacity(self.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Synchronously flushes the elements and the stored length to the backing file by calling [`libc::msync`]
+    pub fn flush(&self) -> Result<()> {
+        self.mapping.sync(0..self.mapping.segment_byte_len(), libc::MS_SYNC)
+    }
+
+    /// Schedules the elements and the stored length to be written to the backing file,
+    /// returning without waiting for the writes to complete
+    pub fn flush_async(&self) -> Result<()> {
+        self.mapping.sync(0..self.mapping.segment_byte_len(), libc::MS_ASYNC)
+    }
+
+    /// Like [`MappedVec::flush`] but additionally calls [`libc::fdatasync`] on the backing file
+    /// so that its size is durable as well
+    pub fn flush_and_sync(&self) -> Result<()> {
+        self.flush()?;
+        raw_memory_mapping::sync_data(self.file.as_raw_fd())
+    }
+
+    fn resize_capacity(&mut self, new_capacity: usize) -> Result<()> {
+        let old_byte_len = self.mapping.segment_byte_len();
+        let new_byte_len = Self::file_byte_len_for(new_capacity);
+
+        if new_byte_len > old_byte_len {
+            allocate(&self.file, old_byte_len, new_byte_len - old_byte_len).map_err(|e| self.with_path(e))?;
+
+            // SAFETY: the file was just grown to be at least as large as the new mapping
+            unsafe { self.mapping.byte_resize(new_byte_len) }
+        } else {
+            // SAFETY: the mapping shrinks, so it still lies within the file
+            unsafe { self.mapping.byte_resize(new_byte_len)? };
+            self.file
+                .set_len(new_byte_len as u64)
+                .map_err(|source| self.with_path(Error::FileResize { path: None, file_len: new_byte_len, source }))
+        }
+    }
+
+    fn with_path(&self, e: Error) -> Error {
+        match self.mapping.path() {
+            Some(path) => e.with_path(path),
+            None => e,
+        }
+    }
+}
+
+/// Grows `file` to cover `byte_offset..byte_offset + byte_len` using [`libc::fallocate`],
+/// falling back to [`libc::ftruncate`] if the filesystem does not support it
+fn allocate(file: &File, byte_offset: usize, byte_len: usize) -> Result<()> {
+    let res = unsafe { libc::fallocate(file.as_raw_fd(), 0, byte_offset as libc::off_t, byte_len as libc::off_t) };
+
+    if res == 0 {
+        return Ok(());
+    }
+
+    let file_len = byte_offset + byte_len;
+    let resize_err = |source| Error::FileResize { path: None, file_len, source };
+
+    match last_os_error() {
+        e if e.raw_os_error() == Some(libc::EOPNOTSUPP) => file.set_len(file_len as u64).map_err(resize_err),
+        e => Err(resize_err(e)),
+    }
+}
+
+impl<T: Copy> Extend<T> for MappedVec<T> {
+    /// # Panics
+    /// panics if the backing file cannot be grown
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0).expect("failed to grow backing file");
+
+        for x in iter {
+            self.push(x).expect("failed to grow backing file");
+        }
+    }
+}
+
+impl<T: Copy> Deref for MappedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T: Copy> DerefMut for MappedVec<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{error::last_os_error, layout::ElemLayout, Error, MemoryMapped, Mode, OpenOptions, ReadOnly, Result};
+use std::{
+    ffi::{CStr, CString},
+    fs::File,
+    mem::{ManuallyDrop, MaybeUninit},
+    os::unix::io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+/// Seals that can be placed on a memfd with [`libc::fcntl`] and [`libc::F_ADD_SEALS`]
+///
+/// Seals can be combined with `|`, e.g. `Seals::SHRINK | Seals::GROW`. Once placed a seal can never be removed.
+/// See `man 2 memfd_create` for a detailed description of the individual seals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Seals(libc::c_int);
+
+impl Seals {
+    /// No seals
+    pub const NONE: Seals = Seals(0);
+    /// Prevents adding further seals, see [`libc::F_SEAL_SEAL`]
+    pub const SEAL: Seals = Seals(libc::F_SEAL_SEAL);
+    /// Prevents the file from being shrunk, see [`libc::F_SEAL_SHRINK`]
+    pub const SHRINK: Seals = Seals(libc::F_SEAL_SHRINK);
+    /// Prevents the file from being grown, see [`libc::F_SEAL_GROW`]
+    pub const GROW: Seals = Seals(libc::F_SEAL_GROW);
+    /// Prevents any modification of the contents, see [`libc::F_SEAL_WRITE`].
+    /// Fails while writable shared mappings of the file exist.
+    pub const WRITE: Seals = Seals(libc::F_SEAL_WRITE);
+    /// Prevents new writable mappings and writes while existing writable mappings stay usable,
+    /// see [`libc::F_SEAL_FUTURE_WRITE`]
+    pub const FUTURE_WRITE: Seals = Seals(libc::F_SEAL_FUTURE_WRITE);
+
+    /// Returns the seals currently placed on the memfd `fd` by calling [`libc::fcntl`] with [`libc::F_GET_SEALS`]
+    pub fn of<F: AsRawFd>(fd: &F) -> Result<Seals> {
+        let seals = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GET_SEALS) };
+
+        if seals < 0 {
+            return Err(Error::Seal { seals: 0, source: last_os_error() });
+        }
+
+        Ok(Seals(seals))
+    }
+
+    /// Places these seals on the memfd `fd` by calling [`libc::fcntl`] with [`libc::F_ADD_SEALS`]
+    pub fn add_to<F: AsRawFd>(self, fd: &F) -> Result<()> {
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_ADD_SEALS, self.0) } != 0 {
+            return Err(Error::Seal { seals: self.0, source: last_os_error() });
+        }
+
+        Ok(())
+    }
+
+    pub fn contains(self, other: Seals) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for Seals {
+    type Output = Seals;
+
+    fn bitor(self, rhs: Seals) -> Seals {
+        Seals(self.0 | rhs.0)
+    }
+}
+
+/// Duplicates `fd` with [`OwnedFd::try_clone`]
+pub(crate) fn duplicate_fd(fd: &OwnedFd) -> Result<OwnedFd> {
+    fd.try_clone()
+        .map_err(|source| Error::DuplicateFd { fd: fd.as_raw_fd(), source })
+}
+
+/// Creates an anonymous memory backed file by calling [`libc::memfd_create`]
+pub(crate) fn memfd_create(name: &CStr, flags: libc::c_uint) -> Result<OwnedFd> {
+    let fd = unsafe { libc::memfd_create(name.as_ptr(), flags) };
+
+    if fd < 0 {
+        return Err(Error::Open { path: None, source: last_os_error() });
+    }
+
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+impl<T> MemoryMapped<[T]> {
+    /// Creates a zero-initialized memfd of `len` elements that allows sealing by calling [`libc::memfd_create`]
+    /// and maps it as a read-write, shared mapping.
+    ///
+    /// The memfd is available through [`MemoryMapped::backing_fd`], e.g. to pass it to another process,
+    /// and can be sealed with [`MemoryMapped::seal`]. `name` is only used for debugging purposes.
+    pub fn memfd(name: &str, len: usize) -> Result<MemoryMapped<[MaybeUninit<T>]>> {
+        let name = CString::new(name).map_err(|e| Error::Open { path: None, source: e.into() })?;
+        let file_len = ElemLayout::slice::<T>().slice_byte_len(len)?;
+        let fd = memfd_create(&name, libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING)?;
+
+        File::from(duplicate_fd(&fd)?)
+            .set_len(file_len as u64)
+            .map_err(|source| Error::FileResize { path: None, file_len, source })?;
+
+        // SAFETY: the memfd was just created, so no other mapping of it exists yet
+        let mut mapped = unsafe {
+            OpenOptions::<[T]>::new()
+                .read(true)
+                .write(true)
+                .len(len)
+                .open_shared_slice_from_fd(&fd)?
+        };
+
+        mapped.mapping.set_backing_fd(fd);
+        Ok(mapped)
+    }
+}
+
+impl<T: ?Sized, M: Mode> MemoryMapped<T, M> {
+    /// Returns the file descriptor backing this mapping if it was created by [`MemoryMapped::memfd`],
+    /// [`crate::SharedMemory::map_slice`] or [`MemoryMapped::recv_from`]
+    pub fn backing_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.mapping.backing_fd()
+    }
+
+    /// Unmaps the memory and returns the file descriptor backing this mapping, see [`MemoryMapped::backing_fd`].
+    ///
+    /// This is required to place [`Seals::WRITE`], which cannot be added while a writable shared mapping exists.
+    pub fn into_backing_fd(self) -> Option<OwnedFd> {
+        let this = ManuallyDrop::new(self);
+        let mut mapping = unsafe { std::ptr::read(&this.mapping) };
+
+        let _ = mapping.close();
+        mapping.take_backing_fd()
+    }
+
+    /// Places `seals` on the memfd backing this mapping, see [`Seals::add_to`].
+    /// Fails with `EBADF` if the mapping is not backed by a memfd.
+    pub fn seal(&self, seals: Seals) -> Result<()> {
+        match self.backing_fd() {
+            Some(fd) => seals.add_to(&fd),
+            None => Err(Error::Seal {
+                seals: seals.0,
+                source: std::io::Error::from_raw_os_error(libc::EBADF),
+            }),
+        }
+    }
+}
+
+impl<T> OpenOptions<[T], ReadOnly> {
+    /// Maps a memfd that is sealed against shrinking and writing as a read-only, shared mapping.
+    ///
+    /// Unlike [`OpenOptions::open_shared_slice_from_fd`] this is safe: the seals guarantee that the file can neither
+    /// be truncated underneath the mapping nor modified while it is mapped.
+    /// Fails with [`Error::MissingSeals`] if [`Seals::SHRINK`] or [`Seals::WRITE`] is not placed on `fd`.
+    pub fn open_sealed_slice_from_fd<F: AsRawFd>(&self, fd: &F) -> Result<MemoryMapped<[MaybeUninit<T>], ReadOnly>> {
+        let required = Seals::SHRINK | Seals::WRITE;
+        let present = Seals::of(fd)?;
+
+        if !present.contains(required) {
+            return Err(Error::MissingSeals { required, present });
+        }
+
+        // SAFETY: the contents of the file can neither change nor be truncated
+        unsafe { self.open_shared_slice_from_fd(fd) }
+    }
+}
@@ -0,0 +1,98 @@
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An access mode of a [`crate::MemoryMapped`]
+///
+/// The mode determines the protection the memory is mapped with and which operations are available.
+/// Mutating operations like [`std::ops::DerefMut`] are only implemented for [`Writable`] modes,
+/// so writing to a read-only mapping fails to compile instead of crashing at runtime.
+///
+/// ```compile_fail,E0599
+/// use memory_mapped::{MemoryMapped, ReadOnly};
+///
+/// let mut mapped: MemoryMapped<[u32], ReadOnly> = MemoryMapped::open_slice("ints.bin").unwrap().init();
+/// mapped.as_slice_mut()[0] = 42;
+/// ```
+///
+/// ```compile_fail,E0594
+/// use memory_mapped::{MemoryMapped, ReadOnly};
+///
+/// let mut mapped: MemoryMapped<[u32], ReadOnly> = MemoryMapped::open_slice("ints.bin").unwrap().init();
+/// mapped[0] = 42;
+/// ```
+pub trait Mode: sealed::Sealed {
+    /// The protection passed to [`libc::mmap`] and [`libc::mprotect`] for mappings of this mode
+    const PROTECTION: libc::c_int;
+}
+
+/// Marker for modes that allow writing to the mapped memory
+pub trait Writable: Mode {}
+
+/// The memory is mapped with [`libc::PROT_READ`]
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOnly;
+
+/// The memory is mapped with [`libc::PROT_READ`] | [`libc::PROT_WRITE`]
+#[derive(Clone, Copy, Debug)]
+pub struct ReadWrite;
+
+/// The memory is mapped with [`libc::PROT_READ`] | [`libc::PROT_EXEC`]
+#[derive(Clone, Copy, Debug)]
+pub struct ReadExec;
+
+impl sealed::Sealed for ReadOnly {}
+impl sealed::Sealed for ReadWrite {}
+impl sealed::Sealed for ReadExec {}
+
+impl Mode for ReadOnly {
+    const PROTECTION: libc::c_int = libc::PROT_READ;
+}
+
+impl Mode for ReadWrite {
+    const PROTECTION: libc::c_int = libc::PROT_READ | libc::PROT_WRITE;
+}
+
+impl Mode for ReadExec {
+    const PROTECTION: libc::c_int = libc::PROT_READ | libc::PROT_EXEC;
+}
+
+impl Writable for ReadWrite {}
+
+/// A runtime memory protection as passed to [`libc::mprotect`]
+///
+/// Protections can be combined with `|`, e.g. `Protection::READ | Protection::WRITE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Protection(libc::c_int);
+
+impl Protection {
+    /// The memory cannot be accessed at all
+    pub const NONE: Protection = Protection(libc::PROT_NONE);
+    /// The memory may be read
+    pub const READ: Protection = Protection(libc::PROT_READ);
+    /// The memory may be written
+    pub const WRITE: Protection = Protection(libc::PROT_WRITE);
+    /// The memory may be executed
+    pub const EXEC: Protection = Protection(libc::PROT_EXEC);
+
+    /// Returns the protection corresponding to the access mode `M`
+    pub fn of<M: Mode>() -> Protection {
+        Protection(M::PROTECTION)
+    }
+
+    pub fn contains(self, other: Protection) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub(crate) fn as_raw(self) -> libc::c_int {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for Protection {
+    type Output = Protection;
+
+    fn bitor(self, rhs: Protection) -> Protection {
+        Protection(self.0 | rhs.0)
+    }
+}
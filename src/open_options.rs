@@ -1,11 +1,30 @@
 use super::MemoryMapped;
 
-use crate::RawMemoryMapping;
-use std::{fs, fs::File, marker::PhantomData, mem::MaybeUninit, os::unix::io::AsRawFd, path::Path};
-
+use crate::{
+    layout::{self, ElemLayout},
+    Advice, Error, HugePageSize, Mode, RawMemoryMapping, ReadOnly, ReadWrite, Result,
+};
+use std::{
+    fs,
+    fs::File,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    os::unix::io::{AsRawFd, RawFd},
+    path::Path,
+    ptr::NonNull,
+};
+
+/// Not exported by [`libc`] for all architectures, see `include/uapi/asm-generic/mman-common.h`
+const MAP_UNINITIALIZED: libc::c_int = 0x4000000;
+
+/// Options used to open a [`MemoryMapped`]
+///
+/// The protection of the mapping is determined by the access mode `M`, see [`Mode`].
+/// The `read` and `write` flags only control how the underlying file is opened.
+///
 /// # Example
 ///
-/// ```rust
+/// ```no_run
 /// use std::mem::MaybeUninit;
 /// use memory_mapped::MemoryMapped;
 ///
@@ -23,45 +42,102 @@ use std::{fs, fs::File, marker::PhantomData, mem::MaybeUninit, os::unix::io::AsR
 ///     println!("{x}");
 /// }
 /// ```
-pub struct OpenOptions<T: ?Sized> {
+pub struct OpenOptions<T: ?Sized, M: Mode = ReadWrite> {
     read: bool,
     write: bool,
     create: bool,
     create_new: bool,
 
     shared: bool,
+    populate: bool,
+    pub(super) relocatable: bool,
+    guard_pages: (usize, usize),
+    extra_mmap_flags: libc::c_int,
+    fixed_addr: Option<NonNull<()>>,
+
+    pub(super) advice: Option<Advice>,
+    pub(super) huge_pages: Option<HugePageSize>,
 
     pub(super) byte_offset: usize,
     pub(super) byte_len: usize,
 
-    _marker: PhantomData<*const T>,
+    _marker: PhantomData<(M, *const T)>,
 }
 
-impl<T: ?Sized> OpenOptions<T> {
+impl<T: ?Sized, M: Mode> OpenOptions<T, M> {
     pub(super) fn get_mmap_protection(&self) -> libc::c_int {
-        use libc::{PROT_NONE, PROT_READ, PROT_WRITE};
+        M::PROTECTION
+    }
+
+    pub(super) fn get_mmap_flags(&self) -> libc::c_int {
+        use libc::{MAP_FIXED_NOREPLACE, MAP_POPULATE, MAP_PRIVATE, MAP_SHARED};
+
+        let sharing = if self.shared { MAP_SHARED } else { MAP_PRIVATE };
+        let populate = if self.populate { MAP_POPULATE } else { 0 };
+        let fixed = if self.fixed_addr.is_some() {
+            MAP_FIXED_NOREPLACE
+        } else {
+            0
+        };
+
+        sharing | populate | fixed | self.extra_mmap_flags
+    }
 
-        let mut protection = PROT_NONE;
+    /// Rejects flag combinations that [`libc::mmap`] would refuse with an undescriptive `EINVAL`
+    /// or that are not permitted by this crate
+    pub(super) fn validate_mmap_flags(&self, page_size: usize, anonymous: bool) -> Result<()> {
+        let invalid = |reason| Err(Error::InvalidFlags { flags: self.get_mmap_flags(), reason });
 
-        if self.read {
-            protection |= PROT_READ;
+        if self.extra_mmap_flags & libc::MAP_SHARED_VALIDATE != 0 && !self.shared {
+            return invalid("MAP_SHARED_VALIDATE requires a shared mapping");
         }
 
-        if self.write {
-            protection |= PROT_WRITE;
+        if self.extra_mmap_flags & libc::MAP_GROWSDOWN != 0 && self.shared {
+            return invalid("MAP_GROWSDOWN cannot be used for shared mappings");
         }
 
-        protection
+        if self.extra_mmap_flags & MAP_UNINITIALIZED != 0 && !anonymous {
+            return invalid("MAP_UNINITIALIZED is only permitted for anonymous mappings");
+        }
+
+        if self.guard_pages != (0, 0) && page_size != crate::page_size() {
+            return invalid("guard pages cannot be combined with huge pages");
+        }
+
+        if self.fixed_addr.is_some() && self.guard_pages != (0, 0) {
+            return invalid("a fixed address cannot be combined with guard pages");
+        }
+
+        if let Some(addr) = self.fixed_addr {
+            if addr.as_ptr() as usize % page_size != self.byte_offset % page_size {
+                return invalid("the fixed address must have the same offset into a page as the mapped segment");
+            }
+        }
+
+        Ok(())
     }
 
-    pub(super) fn get_mmap_flags(&self) -> libc::c_int {
-        use libc::{MAP_PRIVATE, MAP_SHARED};
+    /// Returns the byte lengths of the guard pages before and after the mapping for pages of `page_size` bytes
+    pub(super) fn guard_byte_lens(&self, page_size: usize) -> (usize, usize) {
+        (self.guard_pages.0 * page_size, self.guard_pages.1 * page_size)
+    }
 
-        if self.shared {
-            MAP_SHARED
+    /// Returns the address the mapping starts at if a fixed address was requested, otherwise null
+    pub(super) fn fixed_addr(&self, offset_delta: usize) -> *mut libc::c_void {
+        match self.fixed_addr {
+            Some(addr) => addr.as_ptr().wrapping_byte_sub(offset_delta) as *mut libc::c_void,
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    fn set_mmap_flag(&mut self, flag: libc::c_int, set: bool) -> &mut Self {
+        if set {
+            self.extra_mmap_flags |= flag;
         } else {
-            MAP_PRIVATE
+            self.extra_mmap_flags &= !flag;
         }
+
+        self
     }
 
     pub(super) fn get_fs_open_options(&self) -> fs::OpenOptions {
@@ -74,12 +150,52 @@ impl<T: ?Sized> OpenOptions<T> {
         opts
     }
 
-    fn with_shared(&self, shared: bool) -> Self {
+    pub(super) fn with_shared(&self, shared: bool) -> Self {
         Self { shared, ..*self }
     }
+
+    /// Validates the requested layout against the file referred to by `fd` and fills in the default length
+    fn resolve_layout(&self, elem_layout: ElemLayout, fd: RawFd) -> Result<Self> {
+        let file_len = layout::regular_file_len(fd)?;
+        let byte_len = elem_layout.resolve_byte_len(self.byte_offset, self.byte_len, file_len)?;
+
+        Ok(Self { byte_len, ..*self })
+    }
+
+    /// Validates the requested layout of an anonymous mapping and fills in the default length
+    fn resolve_anonymous_layout(&self, elem_layout: ElemLayout) -> Result<Self> {
+        let byte_len = elem_layout.resolve_byte_len(0, self.byte_len, None)?;
+        Ok(Self { byte_offset: 0, byte_len, ..*self })
+    }
+
+    fn with_mode<N: Mode>(&self) -> OpenOptions<T, N> {
+        OpenOptions {
+            read: self.read,
+            write: self.write,
+            create: self.create,
+            create_new: self.create_new,
+            shared: self.shared,
+            populate: self.populate,
+            relocatable: self.relocatable,
+            guard_pages: self.guard_pages,
+            extra_mmap_flags: self.extra_mmap_flags,
+            fixed_addr: self.fixed_addr,
+            advice: self.advice,
+            huge_pages: self.huge_pages,
+            byte_offset: self.byte_offset,
+            byte_len: self.byte_len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, M: Mode> Default for OpenOptions<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T: ?Sized> OpenOptions<T> {
+impl<T: ?Sized, M: Mode> OpenOptions<T, M> {
     pub fn new() -> Self {
         OpenOptions {
             read: false,
@@ -87,6 +203,13 @@ impl<T: ?Sized> OpenOptions<T> {
             create: false,
             create_new: false,
             shared: false,
+            populate: false,
+            relocatable: true,
+            guard_pages: (0, 0),
+            extra_mmap_flags: 0,
+            fixed_addr: None,
+            advice: None,
+            huge_pages: None,
             byte_offset: 0,
             byte_len: 0,
             _marker: PhantomData,
@@ -122,9 +245,106 @@ impl<T: ?Sized> OpenOptions<T> {
         self.byte_len = byte_len;
         self
     }
+
+    /// Prefaults the whole mapping while it is created by passing [`libc::MAP_POPULATE`] to [`libc::mmap`],
+    /// so that the first accesses do not have to wait for page faults
+    pub fn populate(&mut self, populate: bool) -> &mut Self {
+        self.populate = populate;
+        self
+    }
+
+    /// Controls whether resizing the mapping may move it to a different address, which is allowed by default.
+    /// If relocation is forbidden every resize behaves like [`MemoryMapped::try_resize_in_place_uninit`],
+    /// so pointers into the mapping, e.g. ones handed to foreign code, are never invalidated by a resize.
+    pub fn relocatable(&mut self, relocatable: bool) -> &mut Self {
+        self.relocatable = relocatable;
+        self
+    }
+
+    /// Surrounds the mapping with `before` and `after` inaccessible [`libc::PROT_NONE`] pages, so that out of bounds
+    /// accesses fault deterministically instead of silently reading or corrupting neighbouring memory.
+    ///
+    /// The guard pages adjoin the pages covering the segment, so an overrun only faults immediately if the segment
+    /// ends on a page boundary. Resizing keeps the trailing guard pages directly after the mapping.
+    pub fn guard_pages(&mut self, before: usize, after: usize) -> &mut Self {
+        self.guard_pages = (before, after);
+        self
+    }
+
+    /// Passes [`libc::MAP_SHARED_VALIDATE`] instead of [`libc::MAP_SHARED`] to [`libc::mmap`], so that the kernel
+    /// rejects unknown flags instead of ignoring them. Only valid for shared mappings.
+    pub fn shared_validate(&mut self, shared_validate: bool) -> &mut Self {
+        self.set_mmap_flag(libc::MAP_SHARED_VALIDATE, shared_validate)
+    }
+
+    /// Does not reserve swap space for the mapping by passing [`libc::MAP_NORESERVE`] to [`libc::mmap`].
+    /// Writes may be answered with `SIGSEGV` if no memory is available.
+    pub fn no_reserve(&mut self, no_reserve: bool) -> &mut Self {
+        self.set_mmap_flag(libc::MAP_NORESERVE, no_reserve)
+    }
+
+    /// Locks the pages of the mapping into memory by passing [`libc::MAP_LOCKED`] to [`libc::mmap`].
+    /// Unlike [`MemoryMapped::mlock`] this does not fail if the pages cannot be populated.
+    pub fn locked(&mut self, locked: bool) -> &mut Self {
+        self.set_mmap_flag(libc::MAP_LOCKED, locked)
+    }
+
+    /// Places the mapped segment at `addr` by passing [`libc::MAP_FIXED_NOREPLACE`] to [`libc::mmap`].
+    /// Mapping fails if the address range is already in use.
+    ///
+    /// `addr` must have the same offset into a page as [`OpenOptions::byte_offset`].
+    pub fn fixed_noreplace(&mut self, addr: NonNull<()>) -> &mut Self {
+        self.fixed_addr = Some(addr);
+        self
+    }
+
+    /// Marks the mapping as a thread stack by passing [`libc::MAP_STACK`] to [`libc::mmap`]
+    pub fn stack(&mut self, stack: bool) -> &mut Self {
+        self.set_mmap_flag(libc::MAP_STACK, stack)
+    }
+
+    /// Lets the mapping grow downwards on access of the guard page below it by passing [`libc::MAP_GROWSDOWN`]
+    /// to [`libc::mmap`]. Only valid for private mappings.
+    pub fn grows_down(&mut self, grows_down: bool) -> &mut Self {
+        self.set_mmap_flag(libc::MAP_GROWSDOWN, grows_down)
+    }
+
+    /// Requests that anonymous memory is not zeroed by passing `MAP_UNINITIALIZED` to [`libc::mmap`].
+    /// Only valid for anonymous mappings and ignored unless the kernel was built with
+    /// `CONFIG_MMAP_ALLOW_UNINITIALIZED`, which is only the case for some embedded systems.
+    pub fn uninitialized(&mut self, uninitialized: bool) -> &mut Self {
+        self.set_mmap_flag(MAP_UNINITIALIZED, uninitialized)
+    }
+
+    /// Sets an access pattern hint that is applied with [`libc::madvise`] right after the memory is mapped
+    pub fn advise(&mut self, advice: Advice) -> &mut Self {
+        self.advice = Some(advice);
+        self
+    }
+
+    /// Backs anonymous mappings with huge pages of the given size by passing [`libc::MAP_HUGETLB`] to [`libc::mmap`].
+    /// The mapping is rounded up to a multiple of the huge page size.
+    ///
+    /// This has no effect on file backed mappings, which use huge pages if and only if the file resides on
+    /// a hugetlbfs mount. Mapping fails with [`Error::HugePagesUnavailable`] if no huge pages are reserved,
+    /// see [`crate::huge_page_sizes`].
+    pub fn huge_pages(&mut self, size: HugePageSize) -> &mut Self {
+        self.huge_pages = Some(size);
+        self
+    }
+
+    /// Returns a copy of these options that produces mappings in [`ReadOnly`] mode
+    pub fn read_only(&self) -> OpenOptions<T, ReadOnly> {
+        self.with_mode()
+    }
+
+    /// Returns a copy of these options that produces mappings in [`ReadWrite`] mode
+    pub fn read_write(&self) -> OpenOptions<T, ReadWrite> {
+        self.with_mode()
+    }
 }
 
-impl<T> OpenOptions<[T]> {
+impl<T, M: Mode> OpenOptions<[T], M> {
     pub fn offset(&mut self, element_offset: usize) -> &mut Self {
         self.byte_offset = element_offset * std::mem::size_of::<T>();
         self
@@ -136,87 +356,116 @@ impl<T> OpenOptions<[T]> {
     }
 }
 
-impl<T> OpenOptions<T> {
-    pub fn open<P: AsRef<Path>>(&self, path: P) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
-        let f = self.get_fs_open_options().open(path)?;
-        self.open_from_file(&f)
+impl<T, M: Mode> OpenOptions<T, M> {
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        let path = path.as_ref();
+        let f = self
+            .get_fs_open_options()
+            .open(path)
+            .map_err(|source| Error::Open { path: Some(path.to_owned()), source })?;
+
+        let mut mapped = self.open_from_file(&f).map_err(|e| e.with_path(path))?;
+        mapped.mapping.set_path(path);
+        Ok(mapped)
     }
 
-    pub fn open_from_file(&self, f: &File) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
-        let opts = Self {
-            byte_len: if self.byte_len == 0 {
-                f.metadata()?.len() as usize - self.byte_offset
-            } else {
-                self.byte_len
-            },
-            ..*self
-        };
-
-        Ok(RawMemoryMapping::open(f.as_raw_fd(), &opts)?.into())
+    pub fn open_from_file(&self, f: &File) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        self.open_from_fd(f)
     }
 
-    pub fn open_from_fd<F: AsRawFd>(&self, f: &F) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
-        Ok(RawMemoryMapping::open(f.as_raw_fd(), self)?.into())
+    /// Maps `size_of::<T>()` bytes, or [`OpenOptions::byte_len`] bytes if set, starting at [`OpenOptions::byte_offset`].
+    /// Fails with [`Error::InvalidLayout`] if the offset is misaligned for `T` and [`Error::FileTooSmall`] if the segment
+    /// does not fit into the file.
+    pub fn open_from_fd<F: AsRawFd>(&self, f: &F) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        let opts = self.resolve_layout(ElemLayout::sized::<T>(), f.as_raw_fd())?;
+        Ok(RawMemoryMapping::open(f.as_raw_fd(), &opts)?.into())
     }
 
     /// # Safety
     /// - caller must ensure that the the segment resulting from this call does not overlap with any other segment mapped as shared
     /// - called must ensure that the mapped memory contains a properly initialized object of type `T`
-    pub unsafe fn open_shared<P: AsRef<Path>>(&self, path: P) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
+    pub unsafe fn open_shared<P: AsRef<Path>>(&self, path: P) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
         self.with_shared(true).open(path)
     }
 
     /// # Safety
     /// see [`memory_mapped::OptionOptions::open_shared`]
-    pub unsafe fn open_shared_from_file(&self, f: &File) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
+    pub unsafe fn open_shared_from_file(&self, f: &File) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
         self.with_shared(true).open_from_file(f)
     }
 
     /// # Safety
     /// see [`memory_mapped::OptionOptions::open_shared`]
-    pub unsafe fn open_shared_from_fd<F: AsRawFd>(&self, fd: &F) -> std::io::Result<MemoryMapped<MaybeUninit<T>>> {
+    pub unsafe fn open_shared_from_fd<F: AsRawFd>(&self, fd: &F) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
         self.with_shared(true).open_from_fd(fd)
     }
-}
 
-impl<T> OpenOptions<[T]> {
-    pub fn open_slice<P: AsRef<Path>>(&self, path: P) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
-        let f = self.get_fs_open_options().open(path)?;
-        self.open_slice_from_file(&f)
+    /// Creates a zero-initialized, private mapping that is not backed by a file.
+    /// If no length was set the mapping will be `size_of::<T>()` bytes long.
+    pub fn open_anonymous(&self) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        let opts = self.resolve_anonymous_layout(ElemLayout::sized::<T>())?;
+        Ok(RawMemoryMapping::anonymous(&opts)?.into())
     }
 
-    pub fn open_slice_from_file(&self, f: &File) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
-        let opts = Self {
-            byte_len: if self.byte_len == 0 {
-                f.metadata()?.len() as usize - self.byte_offset
-            } else {
-                self.byte_len
-            },
-            ..*self
-        };
+    /// Creates a zero-initialized, shared mapping that is not backed by a file.
+    /// The mapping will be shared with child processes created by `fork`.
+    pub fn open_shared_anonymous(&self) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        self.with_shared(true).open_anonymous()
+    }
+}
 
-        Ok(RawMemoryMapping::open(f.as_raw_fd(), &opts)?.into())
+impl<T, M: Mode> OpenOptions<[T], M> {
+    pub fn open_slice<P: AsRef<Path>>(&self, path: P) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        let path = path.as_ref();
+        let f = self
+            .get_fs_open_options()
+            .open(path)
+            .map_err(|source| Error::Open { path: Some(path.to_owned()), source })?;
+
+        let mut mapped = self.open_slice_from_file(&f).map_err(|e| e.with_path(path))?;
+        mapped.mapping.set_path(path);
+        Ok(mapped)
     }
 
-    pub fn open_slice_from_fd<F: AsRawFd>(&self, f: &F) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
-        Ok(RawMemoryMapping::open(f.as_raw_fd(), self)?.into())
+    pub fn open_slice_from_file(&self, f: &File) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        self.open_slice_from_fd(f)
+    }
+
+    /// Maps [`OpenOptions::byte_len`] bytes starting at [`OpenOptions::byte_offset`], or the rest of the file if no
+    /// length was set. Fails with [`Error::InvalidLayout`] if the offset is misaligned for `T` or the length is not a
+    /// multiple of `size_of::<T>()` and with [`Error::FileTooSmall`] if the segment does not fit into the file.
+    pub fn open_slice_from_fd<F: AsRawFd>(&self, f: &F) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        let opts = self.resolve_layout(ElemLayout::slice::<T>(), f.as_raw_fd())?;
+        Ok(RawMemoryMapping::open(f.as_raw_fd(), &opts)?.into())
     }
 
     /// # Safety
     /// caller must ensure that the the segment resulting from this call does not overlap with any other segment mapped as shared
-    pub unsafe fn open_shared_slice<P: AsRef<Path>>(&self, path: P) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
+    pub unsafe fn open_shared_slice<P: AsRef<Path>>(&self, path: P) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
         self.with_shared(true).open_slice(path)
     }
 
     /// # Safety
     /// see [`memory_mapped::OptionOptions::open_shared_slice`]
-    pub unsafe fn open_shared_slice_from_file(&self, f: &File) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
+    pub unsafe fn open_shared_slice_from_file(&self, f: &File) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
         self.with_shared(true).open_slice_from_file(f)
     }
 
     /// # Safety
     /// see [`memory_mapped::OptionOptions::open_shared_slice`]
-    pub unsafe fn open_shared_slice_from_fd<F: AsRawFd>(&self, fd: &F) -> std::io::Result<MemoryMapped<[MaybeUninit<T>]>> {
+    pub unsafe fn open_shared_slice_from_fd<F: AsRawFd>(&self, fd: &F) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
         self.with_shared(true).open_slice_from_fd(fd)
     }
+
+    /// Creates a zero-initialized, private mapping of [`OpenOptions::len`] elements that is not backed by a file.
+    pub fn open_anonymous_slice(&self) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        let opts = self.resolve_anonymous_layout(ElemLayout::slice::<T>())?;
+        Ok(RawMemoryMapping::anonymous(&opts)?.into())
+    }
+
+    /// Creates a zero-initialized, shared mapping of [`OpenOptions::len`] elements that is not backed by a file.
+    /// The mapping will be shared with child processes created by `fork`.
+    pub fn open_shared_anonymous_slice(&self) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        self.with_shared(true).open_anonymous_slice()
+    }
 }
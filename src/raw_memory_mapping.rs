@@ -1,5 +1,11 @@
-use crate::OpenOptions;
-use std::{os::unix::io::RawFd, ptr::NonNull};
+use crate::{error::last_os_error, huge_pages, layout::ElemLayout, Advice, Error, Mode, OpenOptions, Result};
+use std::{
+    ops::Range,
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    ptr::NonNull,
+    thread,
+};
 
 /// Return the currently configured page size
 /// by calling [`libc::sysconf`]
@@ -11,45 +17,270 @@ pub struct RawMemoryMapping {
     ptr: NonNull<()>,
     byte_size: usize,
     byte_offset: usize,
+    segment_byte_len: usize,
+    file_byte_offset: usize,
+    page_size: usize,
+    relocatable: bool,
+    guard_before: usize,
+    guard_after: usize,
+    backing_fd: Option<OwnedFd>,
+    backend: Backend,
+    path: Option<PathBuf>,
 }
 
+/// The system interface that created a mapping, which determines how it is released
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backend {
+    /// Created by [`libc::mmap`] and released by [`libc::munmap`]
+    Mmap,
+    /// A System V shared memory segment attached by [`libc::shmat`] and released by [`libc::shmdt`]
+    SysV,
+}
+
+/// Flags of the [`libc::PROT_NONE`] mappings used to reserve address space
+const RESERVE_FLAGS: libc::c_int = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE;
+
 impl RawMemoryMapping {
-    pub fn open<T: ?Sized>(fd: RawFd, open_options: &OpenOptions<T>) -> std::io::Result<RawMemoryMapping> {
-        let offset_delta = open_options.byte_offset % page_size();
-        let mapping_size = open_options.byte_len + offset_delta;
-
-        let ptr = unsafe {
-            libc::mmap(
-                std::ptr::null_mut(),
-                mapping_size,
-                open_options.get_mmap_protection(),
-                open_options.get_mmap_flags(),
-                fd,
-                (open_options.byte_offset - offset_delta) as libc::off_t,
-            )
+    pub fn open<T: ?Sized, M: Mode>(fd: RawFd, open_options: &OpenOptions<T, M>) -> Result<RawMemoryMapping> {
+        let page_size = huge_pages::fd_page_size(fd)?;
+        open_options.validate_mmap_flags(page_size, false)?;
+
+        let offset_delta = open_options.byte_offset % page_size;
+        let mapping_size = (open_options.byte_len + offset_delta).next_multiple_of(page_size);
+        let flags = open_options.get_mmap_flags();
+        let (guard_before, guard_after) = open_options.guard_byte_lens(page_size);
+
+        let ptr = map_guarded(
+            open_options.fixed_addr(offset_delta),
+            mapping_size,
+            open_options.get_mmap_protection(),
+            flags,
+            fd,
+            open_options.byte_offset - offset_delta,
+            (guard_before, guard_after),
+        )
+        .map_err(|source| Error::Map {
+            path: None,
+            byte_offset: open_options.byte_offset,
+            byte_len: open_options.byte_len,
+            protection: open_options.get_mmap_protection(),
+            flags,
+            source,
+        })?;
+
+        let mapping = RawMemoryMapping {
+            ptr,
+            byte_size: mapping_size,
+            byte_offset: offset_delta,
+            segment_byte_len: open_options.byte_len,
+            file_byte_offset: open_options.byte_offset,
+            page_size,
+            relocatable: open_options.relocatable,
+            guard_before,
+            guard_after,
+            backing_fd: None,
+            backend: Backend::Mmap,
+            path: None,
+        };
+
+        mapping.apply_initial_advice(open_options)?;
+        Ok(mapping)
+    }
+
+    /// Creates a mapping that is not backed by any file by passing [`libc::MAP_ANONYMOUS`] to [`libc::mmap`].
+    /// The contents of the mapping are initialized to zero.
+    ///
+    /// If huge pages were requested the mapping is rounded up to a multiple of the huge page size
+    /// and [`libc::MAP_HUGETLB`] is passed as well.
+    pub fn anonymous<T: ?Sized, M: Mode>(open_options: &OpenOptions<T, M>) -> Result<RawMemoryMapping> {
+        let (page_size, huge_page_flags) = match open_options.huge_pages {
+            Some(size) => (size.byte_size()?, size.mmap_flags()),
+            None => (page_size(), 0),
         };
 
-        if ptr == libc::MAP_FAILED {
-            let errno = unsafe { *libc::__errno_location() };
-            return Err(std::io::Error::from_raw_os_error(errno));
+        open_options.validate_mmap_flags(page_size, true)?;
+
+        let mapping_size = open_options.byte_len.next_multiple_of(page_size);
+        let flags = open_options.get_mmap_flags() | libc::MAP_ANONYMOUS | huge_page_flags;
+        let (guard_before, guard_after) = open_options.guard_byte_lens(page_size);
+
+        let ptr = map_guarded(
+            open_options.fixed_addr(0),
+            mapping_size,
+            open_options.get_mmap_protection(),
+            flags,
+            -1,
+            0,
+            (guard_before, guard_after),
+        )
+        .map_err(|source| {
+            // the kernel reports a lack of reserved huge pages as ENOMEM, which is easily mistaken for exhausted memory
+            if huge_page_flags != 0 && source.raw_os_error() == Some(libc::ENOMEM) {
+                return Error::HugePagesUnavailable { page_size, byte_len: mapping_size, source };
+            }
+
+            Error::Map {
+                path: None,
+                byte_offset: 0,
+                byte_len: open_options.byte_len,
+                protection: open_options.get_mmap_protection(),
+                flags,
+                source,
+            }
+        })?;
+
+        let mapping = RawMemoryMapping {
+            ptr,
+            byte_size: mapping_size,
+            byte_offset: 0,
+            segment_byte_len: open_options.byte_len,
+            file_byte_offset: 0,
+            page_size,
+            relocatable: open_options.relocatable,
+            guard_before,
+            guard_after,
+            backing_fd: None,
+            backend: Backend::Mmap,
+            path: None,
+        };
+
+        mapping.apply_initial_advice(open_options)?;
+        Ok(mapping)
+    }
+
+    /// Reserves `byte_len` bytes of address space without committing any memory by mapping them as
+    /// [`libc::PROT_NONE`] with [`libc::MAP_NORESERVE`]. `byte_len` must be a multiple of the page size.
+    pub fn reserve(byte_len: usize) -> Result<RawMemoryMapping> {
+        let ptr = reserve_raw(std::ptr::null_mut(), byte_len).map_err(|source| Error::Map {
+            path: None,
+            byte_offset: 0,
+            byte_len,
+            protection: libc::PROT_NONE,
+            flags: RESERVE_FLAGS,
+            source,
+        })?;
+
+        Ok(RawMemoryMapping {
+            ptr,
+            byte_size: byte_len,
+            byte_offset: 0,
+            segment_byte_len: byte_len,
+            file_byte_offset: 0,
+            page_size: page_size(),
+            relocatable: false,
+            guard_before: 0,
+            guard_after: 0,
+            backing_fd: None,
+            backend: Backend::Mmap,
+            path: None,
+        })
+    }
+
+    /// Attaches the System V shared memory segment `id` by calling [`libc::shmat`] and validates `elem_layout`
+    /// against the size of the segment
+    pub(crate) fn attach_sysv<M: Mode>(id: libc::c_int, elem_layout: ElemLayout) -> Result<RawMemoryMapping> {
+        let mut ds = std::mem::MaybeUninit::<libc::shmid_ds>::uninit();
+
+        if unsafe { libc::shmctl(id, libc::IPC_STAT, ds.as_mut_ptr()) } != 0 {
+            return Err(Error::SysV { call: "shmctl", id, source: last_os_error() });
+        }
+
+        let segment_byte_len = unsafe { ds.assume_init() }.shm_segsz;
+        let byte_len = elem_layout.resolve_byte_len(0, 0, Some(segment_byte_len))?;
+
+        let mut flags = 0;
+
+        if M::PROTECTION & libc::PROT_WRITE == 0 {
+            flags |= libc::SHM_RDONLY;
+        }
+
+        if M::PROTECTION & libc::PROT_EXEC != 0 {
+            flags |= libc::SHM_EXEC;
+        }
+
+        let ptr = unsafe { libc::shmat(id, std::ptr::null(), flags) };
+
+        if ptr as isize == -1 {
+            return Err(Error::SysV { call: "shmat", id, source: last_os_error() });
         }
 
         Ok(RawMemoryMapping {
             ptr: unsafe { NonNull::new_unchecked(ptr as *mut ()) },
-            byte_size: mapping_size,
-            byte_offset: offset_delta,
+            byte_size: segment_byte_len.next_multiple_of(page_size()),
+            byte_offset: 0,
+            segment_byte_len: byte_len,
+            file_byte_offset: 0,
+            page_size: page_size(),
+            relocatable: false,
+            guard_before: 0,
+            guard_after: 0,
+            backing_fd: None,
+            backend: Backend::SysV,
+            path: None,
         })
     }
 
-    pub fn close(&self) {
-        let res = unsafe { libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.byte_size) };
+    fn apply_initial_advice<T: ?Sized, M: Mode>(&self, open_options: &OpenOptions<T, M>) -> Result<()> {
+        match open_options.advice {
+            Some(advice) => {
+                let res = unsafe { self.advise(0..self.segment_byte_len(), advice) };
 
-        assert_eq!(
-            res,
-            0,
-            "munmap failed: {:?}",
-            std::io::Error::from_raw_os_error(unsafe { *libc::__errno_location() })
-        );
+                if res.is_err() {
+                    let _ = self.close();
+                }
+
+                res
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Unmaps the whole mapping by calling [`libc::munmap`], or [`libc::shmdt`] for System V shared memory.
+    /// The mapping must not be used afterwards. Guard pages are released as well.
+    pub fn close(&self) -> Result<()> {
+        let byte_len = self.guard_before + self.byte_size + self.guard_after;
+        let res = match self.backend {
+            Backend::Mmap => unsafe {
+                libc::munmap(
+                    self.ptr.as_ptr().byte_sub(self.guard_before) as *mut libc::c_void,
+                    byte_len,
+                )
+            },
+            Backend::SysV => unsafe { libc::shmdt(self.ptr.as_ptr() as *const libc::c_void) },
+        };
+
+        if res != 0 {
+            return Err(Error::Unmap {
+                path: self.path.clone(),
+                file_byte_offset: self.file_byte_offset,
+                byte_len,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records the path of the mapped file, which is reported in errors of later operations
+    pub fn set_path(&mut self, path: &Path) {
+        self.path = Some(path.to_owned());
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Keeps `fd` open for as long as the mapping exists, e.g. to allow sealing a memfd
+    pub fn set_backing_fd(&mut self, fd: OwnedFd) {
+        self.backing_fd = Some(fd);
+    }
+
+    pub fn backing_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.backing_fd.as_ref().map(|fd| fd.as_fd())
+    }
+
+    pub fn take_backing_fd(&mut self) -> Option<OwnedFd> {
+        self.backing_fd.take()
     }
 
     pub fn segment_ptr(&self) -> NonNull<()> {
@@ -57,24 +288,462 @@ impl RawMemoryMapping {
     }
 
     pub fn segment_byte_len(&self) -> usize {
-        self.byte_size - self.byte_offset
+        self.segment_byte_len
     }
 
-    pub unsafe fn byte_resize(&mut self, new_byte_size: usize) -> std::io::Result<()> {
+    /// Returns the offset of the mapped segment in the backing file, zero for mappings without a file
+    pub fn file_byte_offset(&self) -> usize {
+        self.file_byte_offset
+    }
+
+    /// Returns the size of the pages backing this mapping, which is larger than [`page_size`] for huge page mappings
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Changes the protection of the whole mapping by calling [`libc::mprotect`]
+    pub fn protect(&self, protection: libc::c_int) -> Result<()> {
+        let res = unsafe { libc::mprotect(self.ptr.as_ptr() as *mut libc::c_void, self.byte_size, protection) };
+
+        if res != 0 {
+            return Err(Error::Protect {
+                byte_offset: 0,
+                byte_len: self.segment_byte_len(),
+                protection,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Changes the protection of the pages covering `byte_range` by calling [`libc::mprotect`]
+    pub fn protect_range(&self, byte_range: Range<usize>, protection: libc::c_int) -> Result<()> {
+        if byte_range.is_empty() {
+            return Ok(());
+        }
+
+        let (ptr, len) = self.page_range(byte_range.clone());
+
+        if unsafe { libc::mprotect(ptr, len, protection) } != 0 {
+            return Err(Error::Protect {
+                byte_offset: byte_range.start,
+                byte_len: byte_range.len(),
+                protection,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Translates `byte_range`, given relative to [`RawMemoryMapping::segment_ptr`],
+    /// into a pointer and length covering all pages of the mapping touched by that range
+    pub fn page_range(&self, byte_range: Range<usize>) -> (*mut libc::c_void, usize) {
+        assert!(byte_range.start <= byte_range.end && byte_range.end <= self.segment_byte_len());
+
+        let start = self.byte_offset + byte_range.start;
+        let aligned_start = start - start % self.page_size;
+        let end = (self.byte_offset + byte_range.end)
+            .next_multiple_of(self.page_size)
+            .min(self.byte_size);
+
+        (
+            unsafe { self.ptr.as_ptr().byte_add(aligned_start) as *mut libc::c_void },
+            end - aligned_start,
+        )
+    }
+
+    /// Flushes the pages covering `byte_range` back to the underlying file by calling [`libc::msync`]
+    /// with the given `flags`
+    pub fn sync(&self, byte_range: Range<usize>, flags: libc::c_int) -> Result<()> {
+        if byte_range.is_empty() {
+            return Ok(());
+        }
+
+        let (ptr, len) = self.page_range(byte_range.clone());
+
+        if unsafe { libc::msync(ptr, len, flags) } != 0 {
+            return Err(Error::Sync {
+                byte_offset: byte_range.start,
+                byte_len: byte_range.len(),
+                flags,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Applies `advice` to the pages covering `byte_range` by calling [`libc::madvise`]
+    ///
+    /// # Safety
+    /// if `advice` is destructive the caller must ensure that the resulting contents are valid
+    pub unsafe fn advise(&self, byte_range: Range<usize>, advice: Advice) -> Result<()> {
+        if byte_range.is_empty() {
+            return Ok(());
+        }
+
+        let (ptr, len) = self.page_range(byte_range.clone());
+
+        if libc::madvise(ptr, len, advice.as_raw()) != 0 {
+            return Err(Error::Advise {
+                byte_offset: byte_range.start,
+                byte_len: byte_range.len(),
+                advice,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Faults in every page of the mapping by advising [`Advice::WillNeed`] and reading one byte of each page,
+    /// spreading the pages over up to `threads` threads
+    pub fn prefault(&self, threads: usize) -> Result<()> {
+        unsafe { self.advise(0..self.segment_byte_len, Advice::WillNeed)? };
+
+        let (ptr, len) = self.page_range(0..self.segment_byte_len);
+        let base = ptr as usize;
+        let page_size = self.page_size;
+        let n_pages = len.div_ceil(page_size);
+        let pages_per_thread = n_pages.div_ceil(threads.clamp(1, n_pages.max(1)));
+
+        let touch = move |first_page: usize| {
+            for page in first_page..(first_page + pages_per_thread).min(n_pages) {
+                unsafe { std::ptr::read_volatile((base + page * page_size) as *const u8) };
+            }
+        };
+
+        if pages_per_thread >= n_pages {
+            touch(0);
+        } else {
+            thread::scope(|s| {
+                for first_page in (0..n_pages).step_by(pages_per_thread) {
+                    s.spawn(move || touch(first_page));
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Locks the pages covering `byte_range` into memory by calling [`libc::mlock2`] with the given `flags`
+    pub fn lock(&self, byte_range: Range<usize>, flags: libc::c_uint) -> Result<()> {
+        if byte_range.is_empty() {
+            return Ok(());
+        }
+
+        let (ptr, len) = self.page_range(byte_range.clone());
+
+        if unsafe { libc::mlock2(ptr, len, flags) } != 0 {
+            return Err(Error::Lock {
+                byte_offset: byte_range.start,
+                byte_len: byte_range.len(),
+                flags,
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Unlocks the pages covering `byte_range` by calling [`libc::munlock`]
+    pub fn unlock(&self, byte_range: Range<usize>) -> Result<()> {
+        if byte_range.is_empty() {
+            return Ok(());
+        }
+
+        let (ptr, len) = self.page_range(byte_range.clone());
+
+        if unsafe { libc::munlock(ptr, len) } != 0 {
+            return Err(Error::Unlock {
+                byte_offset: byte_range.start,
+                byte_len: byte_range.len(),
+                source: last_os_error(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Resizes the mapped segment to `new_segment_byte_len` bytes by calling [`libc::mremap`].
+    /// The mapping may be moved to a different address unless it was opened as not relocatable.
+    pub unsafe fn byte_resize(&mut self, new_segment_byte_len: usize) -> Result<()> {
+        self.remap(new_segment_byte_len, self.relocatable)
+    }
+
+    /// Resizes the mapped segment to `new_segment_byte_len` bytes by calling [`libc::mremap`] without
+    /// [`libc::MREMAP_MAYMOVE`], failing with [`Error::WouldMove`] if the mapping cannot be resized in place
+    pub unsafe fn byte_resize_in_place(&mut self, new_segment_byte_len: usize) -> Result<()> {
+        self.remap(new_segment_byte_len, false)
+    }
+
+    unsafe fn remap(&mut self, new_segment_byte_len: usize, may_move: bool) -> Result<()> {
+        let new_byte_size = (self.byte_offset + new_segment_byte_len).next_multiple_of(self.page_size);
+
+        if self.backend == Backend::SysV {
+            return Err(self.remap_error(
+                new_segment_byte_len,
+                0,
+                std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "System V shared memory segments cannot be resized",
+                ),
+            ));
+        }
+
+        if self.guard_before != 0 || self.guard_after != 0 {
+            return self.remap_guarded(new_segment_byte_len, new_byte_size, may_move);
+        }
+
+        let flags = if may_move { libc::MREMAP_MAYMOVE } else { 0 };
+
         let new_ptr = libc::mremap(
             self.ptr.as_ptr() as *mut libc::c_void,
             self.byte_size,
             new_byte_size,
-            libc::MREMAP_MAYMOVE,
+            flags,
         );
 
         if new_ptr == libc::MAP_FAILED {
-            return Err(std::io::Error::from_raw_os_error(*libc::__errno_location()));
+            let source = last_os_error();
+
+            // without MREMAP_MAYMOVE the kernel reports an occupied address range after the mapping as ENOMEM
+            if !may_move && source.raw_os_error() == Some(libc::ENOMEM) {
+                return Err(Error::WouldMove {
+                    byte_len: self.segment_byte_len,
+                    new_byte_len: new_segment_byte_len,
+                });
+            }
+
+            return Err(self.remap_error(new_segment_byte_len, flags, source));
         }
 
         self.ptr = NonNull::new_unchecked(new_ptr as *mut ());
         self.byte_size = new_byte_size;
+        self.segment_byte_len = new_segment_byte_len;
+
+        Ok(())
+    }
+
+    fn remap_error(&self, new_segment_byte_len: usize, flags: libc::c_int, source: std::io::Error) -> Error {
+        Error::Remap {
+            path: self.path.clone(),
+            file_byte_offset: self.file_byte_offset,
+            byte_len: self.segment_byte_len,
+            new_byte_len: new_segment_byte_len,
+            flags,
+            source,
+        }
+    }
+
+    /// Resizes a mapping that is surrounded by guard pages, keeping the trailing guard directly after the mapping
+    unsafe fn remap_guarded(&mut self, new_segment_byte_len: usize, new_byte_size: usize, may_move: bool) -> Result<()> {
+        let old_end = self.ptr.as_ptr().byte_add(self.byte_size) as *mut libc::c_void;
+        let new_end = self.ptr.as_ptr().byte_add(new_byte_size) as *mut libc::c_void;
+
+        let (path, file_byte_offset, byte_len) = (self.path.clone(), self.file_byte_offset, self.segment_byte_len);
+        let remap_err = |flags, source| Error::Remap {
+            path: path.clone(),
+            file_byte_offset,
+            byte_len,
+            new_byte_len: new_segment_byte_len,
+            flags,
+            source,
+        };
+
+        if new_byte_size <= self.byte_size {
+            let released = self.byte_size - new_byte_size;
+
+            if released != 0 {
+                // turn the released pages into the trailing guard and give back the surplus guard pages
+                map_raw(
+                    new_end,
+                    released,
+                    libc::PROT_NONE,
+                    RESERVE_FLAGS | libc::MAP_FIXED,
+                    -1,
+                    0,
+                )
+                .map_err(|source| remap_err(0, source))?;
+                libc::munmap(new_end.byte_add(self.guard_after), released);
+            }
+        } else if !self.grow_guarded_in_place(new_byte_size) {
+            if !may_move {
+                return Err(Error::WouldMove {
+                    byte_len: self.segment_byte_len,
+                    new_byte_len: new_segment_byte_len,
+                });
+            }
+
+            let flags = libc::MREMAP_MAYMOVE | libc::MREMAP_FIXED;
+            let total_byte_len = self.guard_before + new_byte_size + self.guard_after;
+            let reservation = reserve_raw(std::ptr::null_mut(), total_byte_len).map_err(|source| remap_err(0, source))?;
+            let new_addr = reservation.as_ptr().byte_add(self.guard_before) as *mut libc::c_void;
+
+            // moves the mapping into the middle of the reservation, replacing the reserved pages
+            let new_ptr = libc::mremap(
+                self.ptr.as_ptr() as *mut libc::c_void,
+                self.byte_size,
+                new_byte_size,
+                flags,
+                new_addr,
+            );
+
+            if new_ptr == libc::MAP_FAILED {
+                let source = last_os_error();
+                libc::munmap(reservation.as_ptr() as *mut libc::c_void, total_byte_len);
+                return Err(remap_err(flags, source));
+            }
+
+            libc::munmap(
+                self.ptr.as_ptr().byte_sub(self.guard_before) as *mut libc::c_void,
+                self.guard_before,
+            );
+            libc::munmap(old_end, self.guard_after);
+
+            self.ptr = NonNull::new_unchecked(new_ptr as *mut ());
+        }
+
+        self.byte_size = new_byte_size;
+        self.segment_byte_len = new_segment_byte_len;
 
         Ok(())
     }
+
+    /// Grows a guarded mapping into its trailing guard after extending the guard by the same amount,
+    /// returns whether growing in place was possible
+    unsafe fn grow_guarded_in_place(&mut self, new_byte_size: usize) -> bool {
+        let grown = new_byte_size - self.byte_size;
+        let old_end = self.ptr.as_ptr().byte_add(self.byte_size) as *mut libc::c_void;
+
+        if reserve_raw(old_end.byte_add(self.guard_after), grown).is_err() {
+            return false;
+        }
+
+        // the released guard pages are only unmapped for a moment, if another mapping was placed there in between
+        // mremap fails and the guard is restored
+        libc::munmap(old_end, grown);
+
+        let new_ptr = libc::mremap(self.ptr.as_ptr() as *mut libc::c_void, self.byte_size, new_byte_size, 0);
+
+        if new_ptr == libc::MAP_FAILED {
+            if reserve_raw(old_end, grown).is_ok() {
+                libc::munmap(old_end.byte_add(self.guard_after), grown);
+            } else {
+                // the trailing guard was displaced by a foreign mapping and cannot be restored
+                libc::munmap(old_end.byte_add(grown), self.guard_after);
+                self.guard_after = 0;
+            }
+
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Calls [`libc::mmap`] and makes sure that the mapping was placed at `addr` if [`libc::MAP_FIXED_NOREPLACE`] was passed,
+/// since kernels older than 4.17 silently treat the address as a hint
+fn map_raw(
+    addr: *mut libc::c_void,
+    byte_len: usize,
+    protection: libc::c_int,
+    flags: libc::c_int,
+    fd: RawFd,
+    file_offset: usize,
+) -> std::io::Result<NonNull<()>> {
+    let ptr = unsafe { libc::mmap(addr, byte_len, protection, flags, fd, file_offset as libc::off_t) };
+
+    if ptr == libc::MAP_FAILED {
+        return Err(last_os_error());
+    }
+
+    if flags & libc::MAP_FIXED_NOREPLACE != 0 && ptr != addr {
+        unsafe { libc::munmap(ptr, byte_len) };
+        return Err(std::io::Error::from_raw_os_error(libc::EEXIST));
+    }
+
+    Ok(unsafe { NonNull::new_unchecked(ptr as *mut ()) })
+}
+
+/// Maps `byte_len` bytes of [`libc::PROT_NONE`] memory at `addr`, which must not be in use if it is not null
+fn reserve_raw(addr: *mut libc::c_void, byte_len: usize) -> std::io::Result<NonNull<()>> {
+    let flags = if addr.is_null() {
+        RESERVE_FLAGS
+    } else {
+        RESERVE_FLAGS | libc::MAP_FIXED_NOREPLACE
+    };
+    map_raw(addr, byte_len, libc::PROT_NONE, flags, -1, 0)
+}
+
+/// Like [`map_raw`] but surrounds the mapping with the given number of bytes of [`libc::PROT_NONE`] guard pages
+/// by reserving the whole range first and placing the mapping in it with [`libc::MAP_FIXED`]
+fn map_guarded(
+    addr: *mut libc::c_void,
+    byte_len: usize,
+    protection: libc::c_int,
+    flags: libc::c_int,
+    fd: RawFd,
+    file_offset: usize,
+    (guard_before, guard_after): (usize, usize),
+) -> std::io::Result<NonNull<()>> {
+    if guard_before == 0 && guard_after == 0 {
+        return map_raw(addr, byte_len, protection, flags, fd, file_offset);
+    }
+
+    let total_byte_len = guard_before + byte_len + guard_after;
+    let reservation = reserve_raw(std::ptr::null_mut(), total_byte_len)?;
+    let addr = unsafe { reservation.as_ptr().byte_add(guard_before) as *mut libc::c_void };
+
+    map_raw(addr, byte_len, protection, flags | libc::MAP_FIXED, fd, file_offset).inspect_err(|_| unsafe {
+        libc::munmap(reservation.as_ptr() as *mut libc::c_void, total_byte_len);
+    })
+}
+
+/// Calls [`libc::fdatasync`] on `fd`
+pub fn sync_data(fd: RawFd) -> Result<()> {
+    if unsafe { libc::fdatasync(fd) } != 0 {
+        return Err(Error::Sync {
+            byte_offset: 0,
+            byte_len: 0,
+            flags: 0,
+            source: last_os_error(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Converts an element range into a byte range for elements of `elem_size` bytes
+/// in a slice of `len` elements
+///
+/// # Panics
+/// panics if the range is out of bounds, mirroring slice indexing
+pub fn elem_range_to_byte_range<R: std::ops::RangeBounds<usize>>(range: R, len: usize, elem_size: usize) -> Range<usize> {
+    use std::ops::Bound;
+
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+
+    assert!(
+        start <= end,
+        "range start index {start} is greater than range end index {end}"
+    );
+    assert!(
+        end <= len,
+        "range end index {end} out of range for slice of length {len}"
+    );
+
+    start * elem_size..end * elem_size
 }
@@ -0,0 +1,164 @@
+use crate::{page_size, raw_memory_mapping, Advice, Error, MapSafe, RawMemoryMapping, Result};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range, RangeBounds},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A reserved region of address space whose prefix is committed on demand
+///
+/// The whole region is mapped as [`libc::PROT_NONE`] with [`libc::MAP_NORESERVE`] up front, so it does not consume
+/// any memory until it is committed. Committing makes the pages covering the new elements readable and writable
+/// by calling [`libc::mprotect`], so unlike [`crate::MemoryMapped::resize_with`] the base address never moves and
+/// references into the committed prefix stay valid while it grows.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::Reservation;
+///
+/// let r: Reservation<u64> = Reservation::new(1 << 40).unwrap();
+/// r.commit(1024).unwrap();
+///
+/// let first = &r[0];
+/// r.commit(1 << 20).unwrap();
+/// assert_eq!(*first, 0);
+/// ```
+pub struct Reservation<T: MapSafe> {
+    mapping: RawMemoryMapping,
+    len: AtomicUsize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: MapSafe + Sync> Sync for Reservation<T> {}
+unsafe impl<T: MapSafe + Send> Send for Reservation<T> {}
+
+impl<T: MapSafe> Drop for Reservation<T> {
+    fn drop(&mut self) {
+        let _ = self.mapping.close();
+    }
+}
+
+impl<T: MapSafe> Reservation<T> {
+    /// Reserves at least `max_bytes` bytes of address space, rounded up to a multiple of the page size.
+    /// Initially no elements are committed.
+    pub fn new(max_bytes: usize) -> Result<Self> {
+        assert!(std::mem::size_of::<T>() > 0, "zero sized types cannot be mapped");
+        assert!(std::mem::align_of::<T>() <= page_size());
+
+        Ok(Reservation {
+            mapping: RawMemoryMapping::reserve(max_bytes.next_multiple_of(page_size()))?,
+            len: AtomicUsize::new(0),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the number of committed elements
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of elements that can be committed
+    pub fn capacity(&self) -> usize {
+        self.mapping.segment_byte_len() / std::mem::size_of::<T>()
+    }
+
+    /// Commits the first `len` elements by making the pages covering them readable and writable.
+    /// Newly committed elements are zero-initialized, committing fewer elements than are already committed
+    /// has no effect. Fails with [`Error::ReservationExceeded`] if `len` exceeds [`Reservation::capacity`].
+    pub fn commit(&self, len: usize) -> Result<()> {
+        let old_len = self.len();
+
+        if len <= old_len {
+            return Ok(());
+        }
+
+        if len > self.capacity() {
+            return Err(Error::ReservationExceeded {
+                byte_len: len * std::mem::size_of::<T>(),
+                reserved_byte_len: self.mapping.segment_byte_len(),
+            });
+        }
+
+        let byte_range = old_len * std::mem::size_of::<T>()..len * std::mem::size_of::<T>();
+        self.mapping
+            .protect_range(byte_range, libc::PROT_READ | libc::PROT_WRITE)?;
+        self.len.fetch_max(len, Ordering::AcqRel);
+
+        Ok(())
+    }
+
+    /// Resets the elements in `range` to zero and releases the memory of all pages that lie completely within it
+    /// by calling [`libc::madvise`] with [`Advice::DontNeed`].
+    /// If `range` extends to the end of the committed elements they are decommitted and the released pages
+    /// are made inaccessible again.
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds
+    pub fn decommit<R: RangeBounds<usize>>(&mut self, range: R) -> Result<()> {
+        let len = self.len();
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, len, std::mem::size_of::<T>());
+        let truncates = byte_range.end == len * std::mem::size_of::<T>();
+
+        let page_start = byte_range.start.next_multiple_of(page_size());
+        let page_end = if truncates {
+            byte_range.end.next_multiple_of(page_size())
+        } else {
+            byte_range.end - byte_range.end % page_size()
+        };
+
+        if page_start < page_end {
+            // SAFETY: `T: MapSafe`, so the zeroed elements are valid
+            unsafe { self.mapping.advise(page_start..page_end, Advice::DontNeed)? };
+
+            // only whole pages can be released, elements on partially covered pages are zeroed manually
+            self.zero_bytes(byte_range.start..page_start);
+
+            if truncates {
+                self.mapping.protect_range(page_start..page_end, libc::PROT_NONE)?;
+            } else {
+                self.zero_bytes(page_end..byte_range.end);
+            }
+        } else {
+            self.zero_bytes(byte_range.clone());
+        }
+
+        if truncates {
+            *self.len.get_mut() = byte_range.start / std::mem::size_of::<T>();
+        }
+
+        Ok(())
+    }
+
+    fn zero_bytes(&mut self, byte_range: Range<usize>) {
+        unsafe {
+            let start = (self.mapping.segment_ptr().as_ptr() as *mut u8).add(byte_range.start);
+            std::ptr::write_bytes(start, 0, byte_range.len());
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.mapping.segment_ptr().as_ptr() as *const T, self.len()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.mapping.segment_ptr().as_ptr() as *mut T, self.len()) }
+    }
+}
+
+impl<T: MapSafe> Deref for Reservation<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T: MapSafe> DerefMut for Reservation<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{error::last_os_error, memfd::duplicate_fd, Error, MemoryMapped, OpenOptions, Result};
+use std::{
+    ffi::{CStr, CString},
+    mem::MaybeUninit,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    path::PathBuf,
+};
+
+/// A named POSIX shared memory object created or opened with [`libc::shm_open`]
+///
+/// The object can be mapped with [`SharedMemory::map_slice`] or, for more control, by passing it to
+/// [`OpenOptions::open_shared_slice_from_fd`]. Objects created by [`SharedMemory::create`] are removed with
+/// [`libc::shm_unlink`] when the handle is dropped unless [`SharedMemory::unlink_on_drop`] is disabled, so that
+/// crashed processes do not leave orphaned objects behind once the creator exits.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::{MemoryMapped, SharedMemory};
+///
+/// let shm = SharedMemory::create("my_shm", 4096).unwrap();
+/// let mut mapped: MemoryMapped<[u32]> = unsafe { shm.map_slice().unwrap().init() };
+/// mapped[0] = 42;
+/// ```
+pub struct SharedMemory {
+    fd: OwnedFd,
+    name: CString,
+    unlink_on_drop: bool,
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        if self.unlink_on_drop {
+            unsafe { libc::shm_unlink(self.name.as_ptr()) };
+        }
+    }
+}
+
+impl SharedMemory {
+    /// Creates a new shared memory object of `byte_len` zero-initialized bytes that is only accessible
+    /// by the current user. Fails if an object of the same name already exists.
+    pub fn create(name: &str, byte_len: usize) -> Result<Self> {
+        Self::create_with_mode(name, byte_len, 0o600)
+    }
+
+    /// Like [`SharedMemory::create`] but sets the permissions of the object to exactly `mode`.
+    /// Unlike with [`libc::shm_open`] alone the mode is not restricted by the umask of the process.
+    pub fn create_with_mode(name: &str, byte_len: usize, mode: libc::mode_t) -> Result<Self> {
+        let mut shm = Self::shm_open(name, libc::O_RDWR | libc::O_CREAT | libc::O_EXCL, mode)?;
+
+        // also removes the object again if it cannot be set up
+        shm.unlink_on_drop = true;
+
+        let open_err = |source| Error::Open { path: Some(shm.path()), source };
+
+        if unsafe { libc::fchmod(shm.fd.as_raw_fd(), mode) } != 0 {
+            return Err(open_err(last_os_error()));
+        }
+
+        if unsafe { libc::ftruncate(shm.fd.as_raw_fd(), byte_len as libc::off_t) } != 0 {
+            return Err(open_err(last_os_error()));
+        }
+
+        Ok(shm)
+    }
+
+    /// Opens an existing shared memory object for reading and writing
+    pub fn open(name: &str) -> Result<Self> {
+        Self::shm_open(name, libc::O_RDWR, 0)
+    }
+
+    /// Opens an existing shared memory object for reading only.
+    /// It can only be mapped in [`crate::ReadOnly`] mode.
+    pub fn open_read_only(name: &str) -> Result<Self> {
+        Self::shm_open(name, libc::O_RDONLY, 0)
+    }
+
+    /// Removes the shared memory object called `name` by calling [`libc::shm_unlink`], e.g. to clean up after a
+    /// crashed process. Existing mappings of the object stay valid.
+    pub fn unlink(name: &str) -> Result<()> {
+        let name = shm_name(name)?;
+
+        if unsafe { libc::shm_unlink(name.as_ptr()) } != 0 {
+            return Err(Error::Open { path: Some(name_to_path(&name)), source: last_os_error() });
+        }
+
+        Ok(())
+    }
+
+    fn shm_open(name: &str, flags: libc::c_int, mode: libc::mode_t) -> Result<Self> {
+        let name = shm_name(name)?;
+        let fd = unsafe { libc::shm_open(name.as_ptr(), flags | libc::O_CLOEXEC, mode) };
+
+        if fd < 0 {
+            return Err(Error::Open { path: Some(name_to_path(&name)), source: last_os_error() });
+        }
+
+        Ok(SharedMemory {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            name,
+            unlink_on_drop: false,
+        })
+    }
+
+    /// Controls whether the object is removed with [`libc::shm_unlink`] when this handle is dropped.
+    /// This is enabled by default for objects created by this handle.
+    pub fn unlink_on_drop(&mut self, unlink_on_drop: bool) -> &mut Self {
+        self.unlink_on_drop = unlink_on_drop;
+        self
+    }
+
+    /// Returns the name of the object including the leading `/`
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns the current size of the object in bytes by calling [`libc::fstat`]
+    pub fn byte_len(&self) -> Result<usize> {
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+
+        if unsafe { libc::fstat(self.fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Err(Error::Open { path: Some(self.path()), source: last_os_error() });
+        }
+
+        Ok(unsafe { stat.assume_init() }.st_size as usize)
+    }
+
+    /// Maps the whole object as a read-write, shared mapping.
+    /// The mapping keeps a duplicate of the file descriptor, so it can be sent with [`MemoryMapped::send_to`].
+    ///
+    /// # Safety
+    /// see [`OpenOptions::open_shared_slice`]
+    pub unsafe fn map_slice<T>(&self) -> Result<MemoryMapped<[MaybeUninit<T>]>> {
+        let mut mapped = OpenOptions::<[T]>::new()
+            .read(true)
+            .write(true)
+            .open_shared_slice_from_fd(&self.fd)
+            .map_err(|e| e.with_path(&self.path()))?;
+
+        mapped.mapping.set_backing_fd(duplicate_fd(&self.fd)?);
+        Ok(mapped)
+    }
+
+    fn path(&self) -> PathBuf {
+        name_to_path(&self.name)
+    }
+}
+
+impl AsFd for SharedMemory {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for SharedMemory {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Converts `name` into a name suitable for [`libc::shm_open`], which must start with a `/`
+fn shm_name(name: &str) -> Result<CString> {
+    let name = if name.starts_with('/') {
+        name.to_owned()
+    } else {
+        format!("/{name}")
+    };
+
+    CString::new(name).map_err(|e| Error::Open { path: None, source: e.into() })
+}
+
+fn name_to_path(name: &CStr) -> PathBuf {
+    PathBuf::from(name.to_string_lossy().into_owned())
+}
@@ -0,0 +1,226 @@
+use crate::{Error, MapSafe, MemoryMapped, Result, Writable};
+use std::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
+
+/// A mutex that can be placed inside a shared mapping and locked by multiple processes
+///
+/// The mutex is a `pthread_mutex_t` initialized with [`libc::PTHREAD_PROCESS_SHARED`] and
+/// [`libc::PTHREAD_MUTEX_ROBUST`] that is stored directly in front of the protected value.
+/// If a process dies while holding the lock, the next [`SharedMutex::lock`] fails with [`LockError::OwnerDied`]
+/// instead of deadlocking every other process mapping the same memory. The protected value has to be [`MapSafe`],
+/// since a dead owner may have left it partially modified.
+///
+/// The mutex is initialized once by one process, e.g. with [`MemoryMapped::init_shared_mutex`], all other
+/// processes map the already initialized mutex with [`MemoryMapped::assume_init`].
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::{LockError, OpenOptions, SharedMutex};
+///
+/// let mapped = unsafe {
+///     OpenOptions::<SharedMutex<[u64; 16]>>::new()
+///         .read(true)
+///         .write(true)
+///         .byte_offset(4096)
+///         .open_shared("state.bin")
+///         .unwrap()
+/// };
+/// let mapped = mapped.init_shared_mutex([0; 16]).unwrap();
+///
+/// let mut guard = match mapped.lock() {
+///     Ok(guard) => guard,
+///     Err(LockError::OwnerDied(guard)) => {
+///         // repair the state left behind by the dead owner
+///         guard.mark_consistent().unwrap();
+///         guard
+///     },
+///     Err(e) => panic!("{e}"),
+/// };
+///
+/// guard[0] += 1;
+/// ```
+#[repr(C)]
+pub struct SharedMutex<T: MapSafe> {
+    raw: UnsafeCell<libc::pthread_mutex_t>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: MapSafe + Send> Sync for SharedMutex<T> {}
+unsafe impl<T: MapSafe + Send> Send for SharedMutex<T> {}
+
+/// The error returned by [`SharedMutex::lock`] and [`SharedMutex::try_lock`]
+pub enum LockError<G> {
+    /// The previous owner died while holding the lock.
+    /// The lock is acquired nonetheless, but the protected value may be inconsistent.
+    /// Unless [`SharedMutexGuard::mark_consistent`] is called before the guard is dropped the mutex becomes
+    /// [`LockError::Poisoned`].
+    OwnerDied(G),
+    /// A previous owner died and the mutex was unlocked without being marked consistent,
+    /// so it can never be locked again
+    Poisoned,
+    /// Locking failed for any other reason
+    Os(Error),
+}
+
+impl<G> fmt::Debug for LockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::OwnerDied(_) => f.write_str("OwnerDied(..)"),
+            LockError::Poisoned => f.write_str("Poisoned"),
+            LockError::Os(e) => f.debug_tuple("Os").field(e).finish(),
+        }
+    }
+}
+
+impl<G> fmt::Display for LockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::OwnerDied(_) => write!(f, "previous owner of the mutex died while holding the lock"),
+            LockError::Poisoned => write!(f, "mutex is not recoverable since a previous owner died"),
+            LockError::Os(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl<G> std::error::Error for LockError<G> {}
+
+/// Converts the return value of a `pthread_*` call into a [`Result`]
+fn check(call: &'static str, res: libc::c_int) -> Result<()> {
+    match res {
+        0 => Ok(()),
+        errno => Err(Error::Mutex { call, source: std::io::Error::from_raw_os_error(errno) }),
+    }
+}
+
+impl<T: MapSafe> SharedMutex<T> {
+    /// Initializes a new, unlocked mutex protecting `value` in `slot`.
+    /// Must be called exactly once for every mutex, before any process locks it.
+    pub fn init(slot: &mut MaybeUninit<Self>, value: T) -> Result<&mut Self> {
+        let ptr = slot.as_mut_ptr();
+
+        unsafe {
+            let mut attr = MaybeUninit::<libc::pthread_mutexattr_t>::uninit();
+            check(
+                "pthread_mutexattr_init",
+                libc::pthread_mutexattr_init(attr.as_mut_ptr()),
+            )?;
+
+            let res = check(
+                "pthread_mutexattr_setpshared",
+                libc::pthread_mutexattr_setpshared(attr.as_mut_ptr(), libc::PTHREAD_PROCESS_SHARED),
+            )
+            .and_then(|_| {
+                check(
+                    "pthread_mutexattr_setrobust",
+                    libc::pthread_mutexattr_setrobust(attr.as_mut_ptr(), libc::PTHREAD_MUTEX_ROBUST),
+                )
+            })
+            .and_then(|_| {
+                check(
+                    "pthread_mutex_init",
+                    libc::pthread_mutex_init(UnsafeCell::raw_get(&raw const (*ptr).raw), attr.as_ptr()),
+                )
+            });
+
+            libc::pthread_mutexattr_destroy(attr.as_mut_ptr());
+            res?;
+
+            UnsafeCell::raw_get(&raw const (*ptr).data).write(value);
+            Ok(slot.assume_init_mut())
+        }
+    }
+
+    /// Blocks until the lock is acquired by calling [`libc::pthread_mutex_lock`]
+    pub fn lock(&self) -> std::result::Result<SharedMutexGuard<'_, T>, LockError<SharedMutexGuard<'_, T>>> {
+        let res = unsafe { libc::pthread_mutex_lock(self.raw.get()) };
+        self.guard("pthread_mutex_lock", res)
+    }
+
+    /// Acquires the lock if it is not held by anyone by calling [`libc::pthread_mutex_trylock`].
+    /// Returns `None` if the lock is currently held.
+    pub fn try_lock(&self) -> std::result::Result<Option<SharedMutexGuard<'_, T>>, LockError<SharedMutexGuard<'_, T>>> {
+        match unsafe { libc::pthread_mutex_trylock(self.raw.get()) } {
+            libc::EBUSY => Ok(None),
+            res => self.guard("pthread_mutex_trylock", res).map(Some),
+        }
+    }
+
+    fn guard(
+        &self,
+        call: &'static str,
+        res: libc::c_int,
+    ) -> std::result::Result<SharedMutexGuard<'_, T>, LockError<SharedMutexGuard<'_, T>>> {
+        let guard = || SharedMutexGuard { mutex: self, _marker: PhantomData };
+
+        match res {
+            0 => Ok(guard()),
+            libc::EOWNERDEAD => Err(LockError::OwnerDied(guard())),
+            libc::ENOTRECOVERABLE => Err(LockError::Poisoned),
+            _ => Err(LockError::Os(check(call, res).unwrap_err())),
+        }
+    }
+
+    /// Returns a mutable reference to the protected value, which requires no locking
+    /// since no other thread of this process can hold the lock
+    ///
+    /// # Safety
+    /// other processes must not access the mutex at the same time
+    pub unsafe fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+/// Releases the lock of a [`SharedMutex`] when dropped
+pub struct SharedMutexGuard<'a, T: MapSafe> {
+    mutex: &'a SharedMutex<T>,
+    // the lock has to be released by the thread that acquired it
+    _marker: PhantomData<*const ()>,
+}
+
+unsafe impl<T: MapSafe + Sync> Sync for SharedMutexGuard<'_, T> {}
+
+impl<T: MapSafe> SharedMutexGuard<'_, T> {
+    /// Marks the protected value as consistent again after the previous owner died
+    /// by calling [`libc::pthread_mutex_consistent`], so that the mutex can be used normally after this guard is
+    /// dropped. Fails if the previous owner did not die.
+    pub fn mark_consistent(&self) -> Result<()> {
+        check("pthread_mutex_consistent", unsafe {
+            libc::pthread_mutex_consistent(self.mutex.raw.get())
+        })
+    }
+}
+
+impl<T: MapSafe> Drop for SharedMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { libc::pthread_mutex_unlock(self.mutex.raw.get()) };
+    }
+}
+
+impl<T: MapSafe> Deref for SharedMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: MapSafe> DerefMut for SharedMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: MapSafe, M: Writable> MemoryMapped<MaybeUninit<SharedMutex<T>>, M> {
+    /// Initializes the mapped mutex with `value`, see [`SharedMutex::init`]
+    pub fn init_shared_mutex(mut self, value: T) -> Result<MemoryMapped<SharedMutex<T>, M>> {
+        SharedMutex::init(&mut self, value)?;
+
+        // SAFETY: the mutex was just initialized
+        Ok(unsafe { self.assume_init() })
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{raw_memory_mapping, MapSafe, MemoryMapped, Mode, ReadOnly, Writable};
+use std::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::RangeBounds,
+    ptr::NonNull,
+    sync::atomic::{self, Ordering},
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Integer types that can be accessed atomically through a [`SharedView`]
+pub trait AtomicInteger: MapSafe + Copy + sealed::Sealed {
+    /// The corresponding atomic type, e.g. [`atomic::AtomicU32`] for `u32`
+    type Atomic;
+
+    #[doc(hidden)]
+    fn load(atomic: &Self::Atomic, order: Ordering) -> Self;
+
+    #[doc(hidden)]
+    fn store(atomic: &Self::Atomic, value: Self, order: Ordering);
+}
+
+macro_rules! impl_atomic_integer {
+    ($($t:ty => $atomic:ty),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl AtomicInteger for $t {
+                type Atomic = $atomic;
+
+                fn load(atomic: &$atomic, order: Ordering) -> $t {
+                    atomic.load(order)
+                }
+
+                fn store(atomic: &$atomic, value: $t, order: Ordering) {
+                    atomic.store(value, order)
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_integer!(
+    u8 => atomic::AtomicU8,
+    u16 => atomic::AtomicU16,
+    u32 => atomic::AtomicU32,
+    u64 => atomic::AtomicU64,
+    usize => atomic::AtomicUsize,
+    i8 => atomic::AtomicI8,
+    i16 => atomic::AtomicI16,
+    i32 => atomic::AtomicI32,
+    i64 => atomic::AtomicI64,
+    isize => atomic::AtomicIsize,
+);
+
+/// Loads `atomic` with [`Ordering::Relaxed`], the only atomic operation that is allowed on read-only memory,
+/// and only for integers that are no wider than `usize`
+pub(crate) fn load_read_only<A: AtomicInteger>(atomic: &A::Atomic) -> A {
+    const {
+        assert!(
+            std::mem::size_of::<A>() <= std::mem::size_of::<usize>(),
+            "atomic loads from read-only memory are limited to the size of usize"
+        )
+    };
+    A::load(atomic, Ordering::Relaxed)
+}
+
+/// A view of a shared mapping that may be modified by other processes at any time
+///
+/// Handing out `&[T]` for memory that another process writes to concurrently is undefined behaviour,
+/// so this view never creates references to the mapped memory. Elements are only accessed with
+/// [`std::ptr::read_volatile`] and [`std::ptr::write_volatile`] or, for integer fields, atomically.
+/// Since the other processes can write arbitrary bytes the element type has to be [`MapSafe`].
+///
+/// A read-only view is obtained from any mapping with [`MemoryMapped::shared_view`], including the still
+/// uninitialized mappings returned by [`crate::OpenOptions::open_shared_slice`]. Writing through the view requires
+/// [`MemoryMapped::shared_view_mut`], which borrows the mapping mutably, so that this process cannot hold references
+/// to the elements at the same time.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::OpenOptions;
+/// use std::sync::atomic::Ordering;
+///
+/// let mut mapped = unsafe { OpenOptions::<[u64]>::new().read(true).write(true).open_shared_slice("shared.bin").unwrap() };
+/// let view = mapped.shared_view_mut();
+///
+/// view.write(0, 42);
+/// view.store::<u64>(8, 1, Ordering::Release);
+///
+/// let mut buf = [0; 4];
+/// view.copy_to(0.., &mut buf);
+/// ```
+pub struct SharedView<'a, T: MapSafe, M: Mode> {
+    ptr: NonNull<T>,
+    len: usize,
+    _marker: PhantomData<(&'a [T], M)>,
+}
+
+unsafe impl<T: MapSafe + Sync, M: Mode> Sync for SharedView<'_, T, M> {}
+unsafe impl<T: MapSafe + Sync, M: Mode> Send for SharedView<'_, T, M> {}
+
+impl<'a, T: MapSafe, M: Mode> SharedView<'a, T, M> {
+    fn new(ptr: NonNull<T>, len: usize) -> Self {
+        SharedView { ptr, len, _marker: PhantomData }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a pointer to the first element
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Reads the element at `ix` with [`std::ptr::read_volatile`]
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn read(&self, ix: usize) -> T {
+        assert!(
+            ix < self.len,
+            "index {ix} out of bounds for shared view of length {}",
+            self.len
+        );
+        unsafe { self.ptr.as_ptr().add(ix).read_volatile() }
+    }
+
+    /// Copies the elements in `range` into `dst` with [`std::ptr::read_volatile`].
+    /// The copy is not atomic, concurrent writes of other processes may be partially observed.
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds or does not have the same length as `dst`
+    pub fn copy_to<R: RangeBounds<usize>>(&self, range: R, dst: &mut [T]) {
+        let start = self.checked_range(range, dst.len());
+
+        for (ix, elem) in dst.iter_mut().enumerate() {
+            *elem = unsafe { self.ptr.as_ptr().add(start + ix).read_volatile() };
+        }
+    }
+
+    /// Atomically loads the integer at `byte_offset` with [`Ordering::Relaxed`], see [`SharedView::load`].
+    /// Unlike other atomic operations this is allowed on read-only memory, follow it with
+    /// [`atomic::fence`]`(Ordering::Acquire)` to synchronize with the writer.
+    ///
+    /// # Panics
+    /// panics if the integer is out of bounds or `byte_offset` is not aligned for `A`
+    pub fn load_relaxed<A: AtomicInteger>(&self, byte_offset: usize) -> A {
+        load_read_only(self.atomic_at::<A>(byte_offset))
+    }
+
+    fn atomic_at<A: AtomicInteger>(&self, byte_offset: usize) -> &A::Atomic {
+        let byte_len = self.len * std::mem::size_of::<T>();
+        assert!(
+            byte_offset
+                .checked_add(std::mem::size_of::<A>())
+                .is_some_and(|end| end <= byte_len),
+            "byte offset {byte_offset} out of bounds for shared view of {byte_len} bytes"
+        );
+
+        let ptr = unsafe { (self.ptr.as_ptr() as *mut u8).add(byte_offset) };
+        assert!(
+            ptr.cast::<A::Atomic>().is_aligned(),
+            "byte offset {byte_offset} is not aligned for {}",
+            std::any::type_name::<A>()
+        );
+
+        // SAFETY: the integer lies within the mapping and is aligned, atomics have the same layout as the integers
+        unsafe { &*(ptr as *const A::Atomic) }
+    }
+
+    /// Validates `range` and returns its start
+    fn checked_range<R: RangeBounds<usize>>(&self, range: R, len: usize) -> usize {
+        let elem_size = std::mem::size_of::<T>();
+        let byte_range = raw_memory_mapping::elem_range_to_byte_range(range, self.len, elem_size);
+        assert_eq!(
+            byte_range.len() / elem_size,
+            len,
+            "source and destination have different lengths"
+        );
+
+        byte_range.start / elem_size
+    }
+}
+
+impl<T: MapSafe, M: Writable> SharedView<'_, T, M> {
+    /// Atomically loads the integer at `byte_offset`, which is relative to the start of the view
+    /// and may point into a field of an element, e.g. as computed by [`std::mem::offset_of`].
+    /// Read-only views only support [`SharedView::load_relaxed`].
+    ///
+    /// # Panics
+    /// panics if the integer is out of bounds or `byte_offset` is not aligned for `A`
+    pub fn load<A: AtomicInteger>(&self, byte_offset: usize, order: Ordering) -> A {
+        A::load(self.atomic_at::<A>(byte_offset), order)
+    }
+
+    /// Writes `value` to the element at `ix` with [`std::ptr::write_volatile`]
+    ///
+    /// # Panics
+    /// panics if `ix` is out of bounds
+    pub fn write(&self, ix: usize, value: T) {
+        assert!(
+            ix < self.len,
+            "index {ix} out of bounds for shared view of length {}",
+            self.len
+        );
+        unsafe { self.ptr.as_ptr().add(ix).write_volatile(value) }
+    }
+
+    /// Copies `src` into the elements in `range` with [`std::ptr::write_volatile`].
+    /// The copy is not atomic, other processes may observe partially copied data.
+    ///
+    /// # Panics
+    /// panics if `range` is out of bounds or does not have the same length as `src`
+    pub fn copy_from<R: RangeBounds<usize>>(&self, range: R, src: &[T]) {
+        let start = self.checked_range(range, src.len());
+
+        for (ix, elem) in src.iter().enumerate() {
+            unsafe { self.ptr.as_ptr().add(start + ix).write_volatile(std::ptr::read(elem)) };
+        }
+    }
+
+    /// Atomically stores `value` to the integer at `byte_offset`, see [`SharedView::load`]
+    ///
+    /// # Panics
+    /// panics if the integer is out of bounds or `byte_offset` is not aligned for `A`
+    pub fn store<A: AtomicInteger>(&self, byte_offset: usize, value: A, order: Ordering) {
+        A::store(self.atomic_at::<A>(byte_offset), value, order)
+    }
+}
+
+impl<T: MapSafe, M: Mode> MemoryMapped<[T], M> {
+    /// Returns a read-only view that accesses the mapped elements without assuming that this process is their sole
+    /// owner
+    pub fn shared_view(&self) -> SharedView<'_, T, ReadOnly> {
+        SharedView::new(self.mapping.segment_ptr().cast(), self.len())
+    }
+}
+
+impl<T: MapSafe, M: Writable> MemoryMapped<[T], M> {
+    /// Returns a writable view that accesses the mapped elements without assuming that this process is their sole
+    /// owner. No references to the elements can be held while the view exists.
+    ///
+    /// ```compile_fail,E0502
+    /// # use memory_mapped::OpenOptions;
+    /// let mapped = unsafe { OpenOptions::<[u64]>::new().read(true).write(true).open_shared_slice("shared.bin").unwrap() };
+    /// let mut mapped = mapped.init();
+    /// let slice: &[u64] = &mapped;
+    /// mapped.shared_view_mut().write(0, 42);
+    /// assert_eq!(slice[0], 42);
+    /// ```
+    pub fn shared_view_mut(&mut self) -> SharedView<'_, T, M> {
+        SharedView::new(self.mapping.segment_ptr().cast(), self.len())
+    }
+}
+
+impl<T: MapSafe, M: Mode> MemoryMapped<[MaybeUninit<T>], M> {
+    /// Returns a read-only view that accesses the mapped elements without assuming that this process is their sole
+    /// owner. Since every bit pattern is a valid `T` this does not require the mapping to be initialized first.
+    pub fn shared_view(&self) -> SharedView<'_, T, ReadOnly> {
+        SharedView::new(self.mapping.segment_ptr().cast(), self.len())
+    }
+}
+
+impl<T: MapSafe, M: Writable> MemoryMapped<[MaybeUninit<T>], M> {
+    /// Returns a writable view of the still uninitialized elements, see [`MemoryMapped::shared_view_mut`]
+    pub fn shared_view_mut(&mut self) -> SharedView<'_, T, M> {
+        SharedView::new(self.mapping.segment_ptr().cast(), self.len())
+    }
+}
+
+impl<T: MapSafe, M: Mode> MemoryMapped<T, M> {
+    /// Returns a read-only view of the mapped value as a single element, see [`MemoryMapped::shared_view`]
+    pub fn shared_view(&self) -> SharedView<'_, T, ReadOnly> {
+        SharedView::new(self.mapping.segment_ptr().cast(), 1)
+    }
+}
+
+impl<T: MapSafe, M: Writable> MemoryMapped<T, M> {
+    /// Returns a writable view of the mapped value as a single element, see [`MemoryMapped::shared_view_mut`]
+    pub fn shared_view_mut(&mut self) -> SharedView<'_, T, M> {
+        SharedView::new(self.mapping.segment_ptr().cast(), 1)
+    }
+}
+
+impl<T: MapSafe, M: Mode> MemoryMapped<MaybeUninit<T>, M> {
+    /// Returns a read-only view of the mapped value as a single element, see [`MemoryMapped::shared_view`]
+    pub fn shared_view(&self) -> SharedView<'_, T, ReadOnly> {
+        SharedView::new(self.mapping.segment_ptr().cast(), 1)
+    }
+}
+
+impl<T: MapSafe, M: Writable> MemoryMapped<MaybeUninit<T>, M> {
+    /// Returns a writable view of the still uninitialized value, see [`MemoryMapped::shared_view_mut`]
+    pub fn shared_view_mut(&mut self) -> SharedView<'_, T, M> {
+        SharedView::new(self.mapping.segment_ptr().cast(), 1)
+    }
+}
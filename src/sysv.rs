@@ -0,0 +1,128 @@
+use crate::{error::last_os_error, layout::ElemLayout, Error, MemoryMapped, Mode, RawMemoryMapping, Result};
+use std::mem::MaybeUninit;
+
+/// A System V shared memory segment identified by the id returned by [`libc::shmget`]
+///
+/// Segments are attached with [`MemoryMapped::attach_sysv`] or [`MemoryMapped::attach_sysv_slice`] and detached
+/// with [`libc::shmdt`] when the resulting mapping is dropped. A segment persists until it is removed with
+/// [`SysVSegment::remove`] and no process has it attached anymore.
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::{MemoryMapped, SysVSegment};
+///
+/// let segment = SysVSegment::open(0x1234).unwrap();
+/// let mapped: MemoryMapped<[u32]> = unsafe { MemoryMapped::attach_sysv_slice(&segment).unwrap().init() };
+///
+/// println!("{}", mapped[0]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SysVSegment {
+    id: libc::c_int,
+}
+
+/// Metadata of a System V shared memory segment as reported by [`libc::shmctl`] with [`libc::IPC_STAT`]
+#[derive(Clone, Copy, Debug)]
+pub struct SysVSegmentStat {
+    pub key: libc::key_t,
+    pub byte_len: usize,
+    pub mode: libc::mode_t,
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    pub creator_pid: libc::pid_t,
+    pub last_pid: libc::pid_t,
+    pub attach_count: libc::shmatt_t,
+    pub attach_time: libc::time_t,
+    pub detach_time: libc::time_t,
+    pub change_time: libc::time_t,
+}
+
+impl SysVSegment {
+    /// Creates a new segment of `byte_len` zero-initialized bytes with the permissions `mode` by calling
+    /// [`libc::shmget`]. Fails if a segment with the same `key` already exists.
+    /// Pass [`libc::IPC_PRIVATE`] as `key` to always create a new segment.
+    pub fn create(key: libc::key_t, byte_len: usize, mode: libc::c_int) -> Result<Self> {
+        Self::shmget(key, byte_len, libc::IPC_CREAT | libc::IPC_EXCL | (mode & 0o777))
+    }
+
+    /// Looks up the existing segment associated with `key` by calling [`libc::shmget`]
+    pub fn open(key: libc::key_t) -> Result<Self> {
+        Self::shmget(key, 0, 0)
+    }
+
+    /// Refers to the segment with the given id, e.g. one received from another process
+    pub fn from_id(id: libc::c_int) -> Self {
+        SysVSegment { id }
+    }
+
+    fn shmget(key: libc::key_t, byte_len: usize, flags: libc::c_int) -> Result<Self> {
+        let id = unsafe { libc::shmget(key, byte_len, flags) };
+
+        if id < 0 {
+            return Err(Error::SysV { call: "shmget", id: key, source: last_os_error() });
+        }
+
+        Ok(SysVSegment { id })
+    }
+
+    pub fn id(&self) -> libc::c_int {
+        self.id
+    }
+
+    /// Returns the metadata of the segment by calling [`libc::shmctl`] with [`libc::IPC_STAT`]
+    pub fn stat(&self) -> Result<SysVSegmentStat> {
+        let mut ds = MaybeUninit::<libc::shmid_ds>::uninit();
+
+        if unsafe { libc::shmctl(self.id, libc::IPC_STAT, ds.as_mut_ptr()) } != 0 {
+            return Err(Error::SysV { call: "shmctl", id: self.id, source: last_os_error() });
+        }
+
+        let ds = unsafe { ds.assume_init() };
+
+        Ok(SysVSegmentStat {
+            key: ds.shm_perm.__key,
+            byte_len: ds.shm_segsz,
+            mode: ds.shm_perm.mode as libc::mode_t,
+            uid: ds.shm_perm.uid,
+            gid: ds.shm_perm.gid,
+            creator_pid: ds.shm_cpid,
+            last_pid: ds.shm_lpid,
+            attach_count: ds.shm_nattch,
+            attach_time: ds.shm_atime,
+            detach_time: ds.shm_dtime,
+            change_time: ds.shm_ctime,
+        })
+    }
+
+    /// Marks the segment for removal by calling [`libc::shmctl`] with [`libc::IPC_RMID`].
+    /// The segment is destroyed once the last process detached it, existing attachments stay valid.
+    pub fn remove(&self) -> Result<()> {
+        if unsafe { libc::shmctl(self.id, libc::IPC_RMID, std::ptr::null_mut()) } != 0 {
+            return Err(Error::SysV { call: "shmctl", id: self.id, source: last_os_error() });
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, M: Mode> MemoryMapped<T, M> {
+    /// Attaches `segment` by calling [`libc::shmat`] and maps its first `size_of::<T>()` bytes.
+    /// The segment is detached with [`libc::shmdt`] when the mapping is dropped.
+    ///
+    /// # Safety
+    /// see [`crate::OpenOptions::open_shared`]
+    pub unsafe fn attach_sysv(segment: &SysVSegment) -> Result<MemoryMapped<MaybeUninit<T>, M>> {
+        Ok(RawMemoryMapping::attach_sysv::<M>(segment.id, ElemLayout::sized::<T>())?.into())
+    }
+}
+
+impl<T, M: Mode> MemoryMapped<[T], M> {
+    /// Attaches `segment` by calling [`libc::shmat`] and maps the whole segment as a slice,
+    /// its size must be a multiple of `size_of::<T>()`. The segment is detached with [`libc::shmdt`] when the mapping is dropped.
+    ///
+    /// # Safety
+    /// see [`crate::OpenOptions::open_shared_slice`]
+    pub unsafe fn attach_sysv_slice(segment: &SysVSegment) -> Result<MemoryMapped<[MaybeUninit<T>], M>> {
+        Ok(RawMemoryMapping::attach_sysv::<M>(segment.id, ElemLayout::slice::<T>())?.into())
+    }
+}
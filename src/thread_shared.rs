@@ -0,0 +1,84 @@
+use crate::{MemoryMapped, Mode, ReadWrite};
+use std::ops::{Deref, DerefMut};
+
+/// A mapping that is [`Send`] and [`Sync`] regardless of the mapped type
+///
+/// Like [`Box`], a [`MemoryMapped<T>`] is only [`Send`] if `T: Send` and only [`Sync`] if `T: Sync`,
+/// so mappings of types with unsynchronized interior mutability cannot be shared between threads by accident.
+/// This wrapper is created with the unsafe [`MemoryMapped::into_thread_shared`] for raw memory that is shared
+/// deliberately and whose accesses are synchronized by other means.
+///
+/// ```compile_fail,E0277
+/// use memory_mapped::MemoryMapped;
+/// use std::cell::Cell;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// let mapped: MemoryMapped<[Cell<u32>]> = unsafe { MemoryMapped::anonymous_slice(4).unwrap().assume_init() };
+/// assert_sync(&mapped);
+/// ```
+///
+/// ```compile_fail,E0277
+/// use memory_mapped::MemoryMapped;
+/// use std::rc::Rc;
+///
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// let mapped: MemoryMapped<[Option<Rc<u32>>]> = unsafe { MemoryMapped::anonymous_slice(4).unwrap().assume_init() };
+/// assert_send(mapped);
+/// ```
+///
+/// # Example
+/// ```no_run
+/// use memory_mapped::MemoryMapped;
+/// use std::cell::Cell;
+///
+/// let mapped: MemoryMapped<[Cell<u32>]> = unsafe { MemoryMapped::anonymous_slice(4).unwrap().assume_init() };
+///
+/// // SAFETY: every thread only accesses its own element
+/// let shared = unsafe { mapped.into_thread_shared() };
+///
+/// std::thread::scope(|s| {
+///     for i in 0..4 {
+///         let shared = &shared;
+///         s.spawn(move || shared[i].set(i as u32));
+///     }
+/// });
+/// ```
+pub struct ThreadShared<T: ?Sized, M: Mode = ReadWrite> {
+    mmap: MemoryMapped<T, M>,
+}
+
+unsafe impl<T: ?Sized, M: Mode> Sync for ThreadShared<T, M> {}
+unsafe impl<T: ?Sized, M: Mode> Send for ThreadShared<T, M> {}
+
+impl<T: ?Sized, M: Mode> MemoryMapped<T, M> {
+    /// Wraps the mapping in a [`ThreadShared`], which is [`Send`] and [`Sync`] regardless of `T`
+    ///
+    /// # Safety
+    /// the caller must ensure that concurrent accesses to the mapped memory from multiple threads
+    /// are free of data races and that the mapped values may be dropped or used on any thread
+    pub unsafe fn into_thread_shared(self) -> ThreadShared<T, M> {
+        ThreadShared { mmap: self }
+    }
+}
+
+impl<T: ?Sized, M: Mode> ThreadShared<T, M> {
+    pub fn into_inner(self) -> MemoryMapped<T, M> {
+        self.mmap
+    }
+}
+
+impl<T: ?Sized, M: Mode> Deref for ThreadShared<T, M> {
+    type Target = MemoryMapped<T, M>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mmap
+    }
+}
+
+impl<T: ?Sized, M: Mode> DerefMut for ThreadShared<T, M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.mmap
+    }
+}